@@ -0,0 +1,52 @@
+use crate::api::core::CoreApi;
+use crate::download::data::{CanvasBase, IdName};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// Instructure's own instance, which can search every hosted institution.
+pub const SEARCH_DOMAIN: &str = "canvas.instructure.com";
+
+#[derive(Debug, Deserialize)]
+pub struct Account {
+    pub name: String,
+    pub domain: String,
+}
+
+#[derive(Serialize)]
+struct AccountSearch<'a> {
+    name: &'a str,
+    per_page: u32,
+}
+
+impl Account {
+    pub fn into_canvas(self, access_token: String) -> Result<CanvasBase, Box<dyn Error>> {
+        let Self {
+            name,
+            domain,
+        } = self;
+        Ok(CanvasBase {
+            api: CoreApi::new(CoreApi::parse_base_url(&domain)?, access_token),
+            // account search doesn't return ids,
+            // so a canvas is really identified by its domain
+            id: IdName {
+                id: 0,
+                name,
+            },
+        })
+    }
+}
+
+impl CoreApi {
+    pub fn account_search() -> CoreApi {
+        let base_url = CoreApi::parse_base_url(SEARCH_DOMAIN).unwrap();
+        CoreApi::new(base_url, String::new())
+    }
+    
+    pub async fn search_accounts(&self, name: &str) -> Result<Vec<Account>, Box<dyn Error>> {
+        let query = AccountSearch {
+            name,
+            per_page: 40,
+        };
+        self.get_list("accounts/search", &query).await
+    }
+}
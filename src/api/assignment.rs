@@ -0,0 +1,85 @@
+use crate::api::core::{CoreApi, PerPage};
+use crate::api::course::Course;
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct Assignment {
+    pub id: Id,
+    pub name: String,
+    /// Html, None if it's empty or locked.
+    pub description: Option<String>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: Option<DateTime<Local>>,
+    pub due_at: Option<DateTime<Local>>,
+    pub lock_at: Option<DateTime<Local>>,
+    pub unlock_at: Option<DateTime<Local>>,
+    pub points_possible: Option<f64>,
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub submission_types: Vec<String>,
+    /// Empty if it isn't graded with a rubric.
+    #[serde(default)]
+    pub rubric: Vec<RubricCriterion>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RubricCriterion {
+    pub id: String,
+    pub description: Option<String>,
+    pub points: Option<f64>,
+    #[serde(default)]
+    pub ratings: Vec<RubricRating>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RubricRating {
+    pub id: String,
+    pub description: Option<String>,
+    pub points: Option<f64>,
+}
+
+/// What's saved next to an assignment's description.
+#[derive(Serialize)]
+pub struct AssignmentMetadata<'a> {
+    pub id: Id,
+    pub name: &'a str,
+    pub due_at: Option<DateTime<Local>>,
+    pub lock_at: Option<DateTime<Local>>,
+    pub unlock_at: Option<DateTime<Local>>,
+    pub points_possible: Option<f64>,
+    pub submission_types: &'a [String],
+    pub html_url: Option<&'a str>,
+}
+
+impl Assignment {
+    pub fn metadata(&self) -> AssignmentMetadata {
+        AssignmentMetadata {
+            id: self.id,
+            name: &self.name,
+            due_at: self.due_at,
+            lock_at: self.lock_at,
+            unlock_at: self.unlock_at,
+            points_possible: self.points_possible,
+            submission_types: &self.submission_types,
+            html_url: self.html_url.as_deref(),
+        }
+    }
+}
+
+impl Course {
+    pub fn assignments_endpoint(&self) -> String {
+        format!("courses/{}/assignments", self.id)
+    }
+}
+
+impl CoreApi {
+    pub async fn assignments(&self, course: &Course) -> Result<Vec<Assignment>, Box<dyn Error>> {
+        self.get_list(
+            course.assignments_endpoint().as_str(),
+            &PerPage { per_page: 100 },
+        ).await
+    }
+}
@@ -0,0 +1,59 @@
+use crate::api::core::{CoreApi, MAX_PAGES};
+use crate::api::query::{modules, Modules};
+use graphql_client::GraphQLQuery;
+use std::error::Error;
+
+/// The `pageInfo` of a graphql connection.
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+impl CoreApi {
+    /// Queries every page of a connection,
+    /// re-issuing the query with `after` set to the previous page's `endCursor`.
+    /// `variables` makes the query's variables for an `after` cursor,
+    /// and `connection` picks the connection's nodes and page info out of the response.
+    /// Nested connections can be paged the same way with a query per parent node.
+    pub async fn query_connection<T, N, V, C>(&self, variables: V, connection: C)
+        -> Result<Vec<N>, Box<dyn Error>>
+        where
+            T: GraphQLQuery,
+            V: Fn(Option<String>) -> T::Variables,
+            C: Fn(T::ResponseData) -> Option<(Vec<N>, PageInfo)>, {
+        let mut nodes = Vec::new();
+        let mut after = None;
+        for _ in 0..MAX_PAGES {
+            let data = self.query_data::<T>(variables(after)).await?;
+            let (mut page, page_info) = connection(data).ok_or("no connection in graphql response")?;
+            nodes.append(&mut page);
+            if !page_info.has_next_page {
+                return Ok(nodes);
+            }
+            after = Some(page_info.end_cursor.ok_or("hasNextPage but no endCursor")?);
+        }
+        Err(format!("graphql connection has more than {} pages", MAX_PAGES).into())
+    }
+    
+    pub async fn graphql_modules(&self, course_id: &str)
+        -> Result<Vec<modules::ModulesCourseModulesConnectionNodes>, Box<dyn Error>> {
+        self.query_connection::<Modules, _, _, _>(
+            |after| modules::Variables {
+                course_id: course_id.to_owned(),
+                after,
+            },
+            |data| {
+                let connection = data.course?.modules_connection?;
+                let page_info = PageInfo {
+                    has_next_page: connection.page_info.has_next_page,
+                    end_cursor: connection.page_info.end_cursor,
+                };
+                let nodes = connection.nodes?
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                Some((nodes, page_info))
+            },
+        ).await
+    }
+}
@@ -1,24 +1,70 @@
-use crate::api::link::{Link, Links};
+use crate::api::governor::{Governor, MAX_THROTTLED_RETRIES};
+use crate::api::link::{self, LinkType, Links};
+use crate::api::retry::{RetryPolicy, StatusError};
+use crate::api::transport::{self, HttpRequest, HttpResponse, Method, Transport};
 use graphql_client::GraphQLQuery;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
-use surf::middleware::HttpClient;
-use surf::{Request, Response};
 use crate::download::data::Id;
+use crate::download::html::DocumentFormat;
 use std::error::Error;
 use crate::util::future::FutureIterator;
-use http_types::headers::HeaderName;
+use http_types::StatusCode;
+use url::Url;
+use serde_json::Value;
+use std::collections::HashSet;
+use futures::stream::{self, Stream, StreamExt};
+use itertools::Itertools;
+use std::time::Duration;
+use std::sync::Arc;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CoreApi {
-    pub domain: String,
+    /// Always ends in a '/', so endpoints can be appended.
+    pub base_url: Url,
+    // kept in state::Credentials instead, so it never ends up in sync.json
+    #[serde(skip)]
     pub authorization: String,
+    // from state::Settings
+    #[serde(skip)]
+    pub retry_policy: RetryPolicy,
+    // also from state::Settings, for what's fetched
+    #[serde(skip)]
+    pub document_format: DocumentFormat,
+    // surf, unless swapped out for tests or offline debugging
+    #[serde(skip, default = "transport::default")]
+    pub transport: Arc<dyn Transport>,
 }
 
-const AUTHORIZATION_PREFIX: &str = "Bearer ";
+pub(crate) const AUTHORIZATION_PREFIX: &str = "Bearer ";
 
-fn header_name(name: &str) -> HeaderName {
-    name.parse().unwrap()
+fn header_number(resp: &HttpResponse, name: &str) -> Option<f64> {
+    resp.header(name).and_then(|it| it.trim().parse().ok())
+}
+
+fn check_status(method: &'static str, url: &str, resp: &HttpResponse) -> Result<(), StatusError> {
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(StatusError {
+            method,
+            url: url.to_owned(),
+            status,
+        });
+    }
+    Ok(())
+}
+
+/// Canvas throttles with 403 Forbidden (Rate Limit Exceeded),
+/// but other 403s are real errors, left to `check_status`.
+async fn is_throttled(resp: &mut HttpResponse) -> Result<bool, Box<dyn Error>> {
+    match resp.status() {
+        StatusCode::TooManyRequests => Ok(true),
+        StatusCode::Forbidden => {
+            let body = resp.body_string().await?;
+            Ok(body.contains("Rate Limit Exceeded"))
+        }
+        _ => Ok(false),
+    }
 }
 
 impl CoreApi {
@@ -30,18 +76,71 @@ impl CoreApi {
         })
     }
     
-    pub fn new(root_url: String, access_token: String) -> CoreApi {
+    /// Accepts a bare domain like canvas.example.edu, which means https,
+    /// or a full url like http://localhost:3000/canvas.
+    pub fn parse_base_url(base_url: &str) -> Result<Url, Box<dyn Error>> {
+        let base_url = if base_url.contains("://") {
+            base_url.to_owned()
+        } else {
+            format!("https://{}", base_url)
+        };
+        let mut url = Url::parse(&base_url)
+            .map_err(|e| format!("invalid canvas url {}: {}", base_url, e))?;
+        match url.scheme() {
+            "https" | "http" => {}
+            scheme => return Err(format!("canvas url {} must be http or https, not {}", url, scheme).into()),
+        }
+        if url.host_str().is_none() {
+            return Err(format!("canvas url {} has no host", url).into());
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(format!("canvas url {} can't have a query or fragment", url).into());
+        }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(url)
+    }
+    
+    pub fn new(base_url: Url, access_token: String) -> CoreApi {
         let mut auth = access_token;
         auth.insert_str(0, AUTHORIZATION_PREFIX);
-        CoreApi { domain: root_url, authorization: auth }
+        CoreApi {
+            base_url,
+            authorization: auth,
+            retry_policy: RetryPolicy::default(),
+            document_format: DocumentFormat::default(),
+            transport: transport::default(),
+        }
+    }
+    
+    /// The host, plus the port and path if they aren't the defaults,
+    /// e.x. canvas.example.edu or localhost:3000/canvas.
+    /// This is what identifies a canvas, e.x. in the credentials.
+    pub fn domain(&self) -> String {
+        let url = &self.base_url;
+        let mut domain = url.host_str().unwrap_or_default().to_owned();
+        if let Some(port) = url.port() {
+            domain.push_str(&format!(":{}", port));
+        }
+        domain.push_str(url.path().trim_end_matches('/'));
+        domain
     }
     
     pub fn access_token(&self) -> &str {
-        &self.authorization[AUTHORIZATION_PREFIX.len()..]
+        // empty when just deserialized
+        self.authorization
+            .get(AUTHORIZATION_PREFIX.len()..)
+            .unwrap_or("")
+    }
+    
+    pub fn set_access_token(&mut self, access_token: &str) {
+        self.authorization = format!("{}{}", AUTHORIZATION_PREFIX, access_token);
     }
     
     fn api_url(&self, version: &str, endpoint: &str) -> String {
-        format!("https://{}/api/{}/{}", self.domain, version, endpoint)
+        format!("{}api/{}/{}", self.base_url, version, endpoint)
     }
     
     fn rest_url(&self, endpoint: &str) -> String {
@@ -49,67 +148,173 @@ impl CoreApi {
     }
     
     fn download_url(&self, id: &Id) -> String {
-        format!("https://{}/files/{}/download?download_frd=1", self.domain, id)
+        format!("{}files/{}/download?download_frd=1", self.base_url, id)
     }
     
-    fn raw_request(&self, url: impl AsRef<str>) -> Request<impl HttpClient> {
-        surf::get(url)
-            .set_header(header_name("Authorization"), &self.authorization)
+    /// Sends requests with `transport` instead of surf.
+    pub fn with_transport(self, transport: Arc<dyn Transport>) -> Self {
+        Self {
+            transport,
+            ..self
+        }
+    }
+    
+    fn raw_request(&self, method: Method, url: String) -> HttpRequest {
+        let mut request = HttpRequest::new(method, url);
+        // anonymous endpoints like account search shouldn't be sent a token
+        if !self.access_token().is_empty() {
+            request.headers.push(("Authorization".into(), self.authorization.clone()));
+        }
+        request
     }
     
-    pub async fn download(&self, id: &Id) -> Result<Response, Box<dyn Error>> {
-        let resp = self.raw_request(self.download_url(id)).await?;
+    /// Sends `request`, pacing it with the token's `Governor`,
+    /// and sending it again while it's throttled.
+    async fn send(&self, url: &str, request: HttpRequest) -> Result<HttpResponse, Box<dyn Error>> {
+        let governor = Governor::for_token(&self.domain(), &self.authorization);
+        let mut attempt = 0;
+        loop {
+            let permit = governor.acquire().await;
+            let mut resp = self.transport.send(request.clone()).await?;
+            drop(permit);
+            governor.update(
+                header_number(&resp, "X-Rate-Limit-Remaining"),
+                header_number(&resp, "X-Request-Cost"),
+            );
+            if !is_throttled(&mut resp).await? {
+                return Ok(resp);
+            }
+            if attempt == MAX_THROTTLED_RETRIES {
+                return Err(format!("{} was still rate limited after {} retries", url, attempt).into());
+            }
+            let retry_after = header_number(&resp, "Retry-After")
+                .filter(|it| it.is_finite() && *it >= 0.0)
+                .map(Duration::from_secs_f64);
+            governor.throttled(retry_after, attempt);
+            attempt += 1;
+        }
+    }
+    
+    /// Not retried by itself, since reading the body can fail too, so retry them together.
+    pub async fn download(&self, id: &Id) -> Result<HttpResponse, Box<dyn Error>> {
+        let url = self.download_url(id);
+        let resp = self.send(&url, self.raw_request(Method::Get, url.clone())).await?;
+        check_status("GET", &url, &resp)?;
         Ok(resp)
     }
     
-    fn request(&self, endpoint: &str, query: &impl Serialize)
-               -> Result<Request<impl HttpClient>, Box<dyn Error>> {
-        let url = self.rest_url(endpoint);
-        let request = self.raw_request(url);
-        let request = request.set_query(query)?;
-        Ok(request)
+    fn request(&self, endpoint: &str, query: &impl Serialize) -> Result<HttpRequest, Box<dyn Error>> {
+        let mut url = Url::parse(&self.rest_url(endpoint))?;
+        let pairs = query_pairs(query)?;
+        if !pairs.is_empty() {
+            url.query_pairs_mut().extend_pairs(pairs);
+        }
+        Ok(self.raw_request(Method::Get, url.to_string()))
     }
     
     pub async fn get<Q, T>(&self, endpoint: &str, query: &Q) -> Result<T, Box<dyn Error>>
         where
             Q: Serialize,
             T: DeserializeOwned, {
-        let req = self.request(endpoint, query)?;
-        let o: T = req.recv_json().await?;
-        Ok(o)
+        self.retry(endpoint, move || async move {
+            let mut resp = self.send(endpoint, self.request(endpoint, query)?).await?;
+            check_status("GET", endpoint, &resp)?;
+            let o: T = resp.body_json().await?;
+            Ok(o)
+        }).await
     }
     
+    /// Canvas only ever links the current, next, prev, first, and last pages,
+    /// so if the last page's number is known, all the pages are requested at once,
+    /// and otherwise next links are followed one at a time.
     pub async fn get_list<Q, T>(&self, endpoint: &str, query: &Q) -> Result<Vec<T>, Box<dyn Error>>
         where
             Q: Serialize,
             T: DeserializeOwned, {
-        let req = self.request(endpoint, query).unwrap();
-        let mut resp = req.await?;
-        let mut pages: Vec<T> = resp.body_json().await?;
-        let links = resp.header(&header_name("Link"))
-            .and_then(|it| it.first()) // TODO see how surf parses multi value headers
-            .map(|it| it.as_str())
-            .and_then(Links::of)
-            .unwrap_or_default();
-        let rest_page_results = links
-            .iter()
-            .filter(|it| !it.current)
-            .map(|link| self.get_link(link))
-            .join_all()
-            .await;
-        for page_result in rest_page_results {
-            let mut page = page_result?;
-            pages.append(&mut page);
+        let first = self.get_first_page(endpoint, query).await?;
+        let mut items = first.items;
+        let next = match first.next {
+            None => return dedup(items),
+            Some(it) => it,
+        };
+        let next_page = link::page_number(&next);
+        let last_page = first.last.as_deref().and_then(link::page_number);
+        match (next_page, last_page) {
+            (Some(next_page), Some(last_page)) if next_page <= last_page => {
+                if last_page > MAX_PAGES {
+                    return Err(too_many_pages(endpoint));
+                }
+                let pages = (next_page..=last_page)
+                    .filter_map(|page| link::with_page_number(&next, page))
+                    .map(|url| self.get_page(url))
+                    .join_all()
+                    .await;
+                for page in pages {
+                    items.append(&mut page?.items);
+                }
+            }
+            _ => {
+                let mut next = Some(next);
+                let mut count = 1;
+                while let Some(url) = next {
+                    count += 1;
+                    if count > MAX_PAGES {
+                        return Err(too_many_pages(endpoint));
+                    }
+                    let mut page = self.get_page(url).await?;
+                    items.append(&mut page.items);
+                    next = page.next;
+                }
+            }
         }
-        Ok(pages)
+        dedup(items)
     }
     
-    async fn get_link<T: DeserializeOwned>(&self, link: &Link<'_>) -> Result<Vec<T>, Box<dyn Error>> {
-        let list = self
-            .raw_request(link.url)
-            .recv_json()
-            .await?;
-        Ok(list)
+    /// Like `get_list`, but yields items as soon as their page arrives.
+    /// Pages are requested one at a time, so dropping the stream early stops requesting them.
+    pub fn stream_list<'a, Q, T>(&'a self, endpoint: &'a str, query: &'a Q)
+        -> impl Stream<Item = Result<T, Box<dyn Error>>> + 'a
+        where
+            Q: Serialize,
+            T: DeserializeOwned + 'a, {
+        let pages = PageStream {
+            api: self,
+            endpoint,
+            query,
+            cursor: Some(Cursor::First),
+            pages: 0,
+            ids: HashSet::new(),
+        };
+        stream::unfold(pages, |mut pages| async move {
+            let page = pages.next_page().await?;
+            Some((page, pages))
+        })
+            .map(|page| {
+                let items = match page {
+                    Ok(items) => items
+                        .into_iter()
+                        .map(|it| serde_json::from_value(it).map_err(|e| e.into()))
+                        .collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                stream::iter(items)
+            })
+            .flatten()
+    }
+    
+    async fn get_first_page(&self, endpoint: &str, query: &impl Serialize) -> Result<Page, Box<dyn Error>> {
+        self.retry(endpoint, move || async move {
+            let resp = self.send(endpoint, self.request(endpoint, query)?).await?;
+            Page::of(endpoint, resp).await
+        }).await
+    }
+    
+    async fn get_page(&self, url: String) -> Result<Page, Box<dyn Error>> {
+        let url = url.as_str();
+        self.retry(url, move || async move {
+            let resp = self.send(url, self.raw_request(Method::Get, url.to_owned())).await?;
+            Page::of(url, resp).await
+        }).await
     }
     
     pub async fn get_filtered_list<Q, T, U>(&self, endpoint: &str, query: &Q)
@@ -125,15 +330,136 @@ impl CoreApi {
         Ok(list)
     }
     
+    /// Like `query`, but turns graphql errors and missing data into an `Err`.
+    pub async fn query_data<T: GraphQLQuery>(&self, vars: T::Variables)
+        -> Result<T::ResponseData, Box<dyn Error>> {
+        let resp = self.query::<T>(vars).await?;
+        if let Some(errors) = resp.errors.filter(|it| !it.is_empty()) {
+            return Err(errors.iter().join("; ").into());
+        }
+        let data = resp.data.ok_or("no data in graphql response")?;
+        Ok(data)
+    }
+    
     pub async fn query<T: GraphQLQuery>(&self, vars: T::Variables)
         -> Result<graphql_client::Response<T::ResponseData>, Box<dyn Error>> {
         let query = T::build_query(vars);
-        let resp = surf::post(self.api_url("graphql", ""))
-            .set_header(header_name("Authorization"), &self.authorization)
-            .body_json(&query)?
-            .recv_json()
-            .await?;
-        Ok(resp)
+        let url = format!("{}api/graphql", self.base_url);
+        let mut request = self.raw_request(Method::Post, url.clone());
+        request.headers.push(("Content-Type".into(), "application/json".into()));
+        request.body = serde_json::to_vec(&query)?;
+        let mut resp = self.send(&url, request).await?;
+        check_status("POST", &url, &resp)?;
+        let data = resp.body_json().await?;
+        Ok(data)
+    }
+}
+
+/// A query's pairs, for any transport.
+/// surf's `set_query` can't encode arrays at all, so here an array is its key repeated,
+/// e.x. `include[]=a&include[]=b`, which is how canvas takes lists,
+/// and the url encodes the brackets as `include%5B%5D`. Nulls are left out.
+fn query_pairs(query: &impl Serialize) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let object = match serde_json::to_value(query)? {
+        Value::Object(it) => it,
+        _ => return Err("query must be a struct or map".into()),
+    };
+    let mut pairs = Vec::new();
+    for (key, value) in object {
+        let values = match value {
+            Value::Array(it) => it,
+            it => vec![it],
+        };
+        for value in values {
+            let value = match value {
+                Value::Null => continue,
+                Value::String(it) => it,
+                it => it.to_string(),
+            };
+            pairs.push((key.clone(), value));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Stops runaway pagination, e.x. if canvas keeps linking the same next page.
+pub(super) const MAX_PAGES: u64 = 1000;
+
+fn too_many_pages(endpoint: &str) -> Box<dyn Error> {
+    format!("{} has more than {} pages", endpoint, MAX_PAGES).into()
+}
+
+/// Items are kept as json until every page is in, so they can be de-duplicated by id,
+/// since pages can shift if something is added while paginating.
+struct Page {
+    items: Vec<Value>,
+    next: Option<String>,
+    last: Option<String>,
+}
+
+impl Page {
+    async fn of(url: &str, mut resp: HttpResponse) -> Result<Self, Box<dyn Error>> {
+        check_status("GET", url, &resp)?;
+        let items = resp.body_json().await?;
+        let links = resp.header("Link")
+            .and_then(Links::of)
+            .unwrap_or_default();
+        Ok(Self {
+            items,
+            next: links.url(LinkType::Next).map(|it| it.to_owned()),
+            last: links.url(LinkType::Last).map(|it| it.to_owned()),
+        })
+    }
+}
+
+fn dedup_into(ids: &mut HashSet<String>, items: Vec<Value>) -> impl Iterator<Item = Value> + '_ {
+    items
+        .into_iter()
+        .filter(move |it| match it.get("id") {
+            None => true,
+            Some(id) => ids.insert(id.to_string()),
+        })
+}
+
+fn dedup<T: DeserializeOwned>(items: Vec<Value>) -> Result<Vec<T>, Box<dyn Error>> {
+    let items = dedup_into(&mut HashSet::new(), items)
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<T>, _>>()?;
+    Ok(items)
+}
+
+enum Cursor {
+    First,
+    Next(String),
+}
+
+/// The state of `CoreApi::stream_list`.
+struct PageStream<'a, Q> {
+    api: &'a CoreApi,
+    endpoint: &'a str,
+    query: &'a Q,
+    // None once there are no more pages
+    cursor: Option<Cursor>,
+    pages: u64,
+    ids: HashSet<String>,
+}
+
+impl<'a, Q: Serialize> PageStream<'a, Q> {
+    async fn next_page(&mut self) -> Option<Result<Vec<Value>, Box<dyn Error>>> {
+        let cursor = self.cursor.take()?;
+        self.pages += 1;
+        if self.pages > MAX_PAGES {
+            return Some(Err(too_many_pages(self.endpoint)));
+        }
+        let page = match cursor {
+            Cursor::First => self.api.get_first_page(self.endpoint, self.query).await,
+            Cursor::Next(url) => self.api.get_page(url).await,
+        };
+        // on errors the cursor stays None, ending the stream
+        Some(page.map(|page| {
+            self.cursor = page.next.map(Cursor::Next);
+            dedup_into(&mut self.ids, page.items).collect()
+        }))
     }
 }
 
@@ -1,10 +1,14 @@
-use crate::api::core::Id;
+use crate::api::core::{CoreApi, PerPage};
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
 use serde::Deserialize;
+use std::error::Error;
 
 #[derive(Deserialize)]
 pub(super) struct MaybeCourse {
     id: Id,
     name: Option<String>,
+    created_at: Option<DateTime<Local>>,
     // other fields not needed
 }
 
@@ -12,13 +16,16 @@ pub(super) struct MaybeCourse {
 pub struct Course {
     pub id: Id,
     pub name: String,
+    pub created_at: DateTime<Local>,
 }
 
 impl From<MaybeCourse> for Option<Course> {
     fn from(course: MaybeCourse) -> Self {
-        let MaybeCourse { id, name } = course;
+        // courses restricted by date only have an id
+        let MaybeCourse { id, name, created_at } = course;
         let name = name?;
-        Some(Course { id, name })
+        let created_at = created_at?;
+        Some(Course { id, name, created_at })
     }
 }
 
@@ -27,3 +34,16 @@ impl Course {
         format!("courses/{}/modules", self.id)
     }
 }
+
+impl CoreApi {
+    pub async fn courses(&self) -> Result<Vec<Course>, Box<dyn Error>> {
+        let courses = self
+            .get_filtered_list::<PerPage, MaybeCourse, Course>(
+                "courses",
+                &PerPage { per_page: 100 },
+            )
+            .await?
+            .collect();
+        Ok(courses)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::api::core::{no_query, CoreApi, PerPage};
+use crate::api::course::Course;
+use crate::api::folder;
+use crate::download::data::Id;
+use crate::download::html::{escape, format_time};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct Author {
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscussionTopic {
+    pub id: Id,
+    pub title: String,
+    /// Html.
+    pub message: Option<String>,
+    pub posted_at: Option<DateTime<Local>>,
+    pub created_at: Option<DateTime<Local>>,
+    /// None until someone replies.
+    pub last_reply_at: Option<DateTime<Local>>,
+    pub author: Option<Author>,
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<folder::File>,
+    /// Only for announcements, e.x. course_101.
+    pub context_code: Option<String>,
+}
+
+impl DiscussionTopic {
+    pub fn view_endpoint(&self, course: &Course) -> String {
+        format!("{}/{}/view", course.discussion_topics_endpoint(), self.id)
+    }
+    
+    pub fn author_name(&self) -> &str {
+        self.author
+            .as_ref()
+            .and_then(|it| it.display_name.as_deref())
+            .or_else(|| self.user_name.as_deref())
+            .unwrap_or("unknown")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Participant {
+    pub id: Id,
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+    pub id: Id,
+    pub user_id: Option<Id>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: Option<DateTime<Local>>,
+    /// Html, None if it was deleted.
+    pub message: Option<String>,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub attachments: Vec<folder::File>,
+    #[serde(default)]
+    pub replies: Vec<Entry>,
+}
+
+/// A topic's whole thread of replies.
+#[derive(Debug, Deserialize, Default)]
+pub struct TopicView {
+    #[serde(default)]
+    pub participants: Vec<Participant>,
+    #[serde(default)]
+    pub view: Vec<Entry>,
+}
+
+impl TopicView {
+    /// Every reply's attachments, and the replies' replies' attachments, etc.
+    pub fn attachments(&self) -> Vec<&folder::File> {
+        fn add<'a>(entries: &'a [Entry], attachments: &mut Vec<&'a folder::File>) {
+            for entry in entries {
+                attachments.extend(&entry.attachments);
+                add(&entry.replies, attachments);
+            }
+        }
+        let mut attachments = Vec::new();
+        add(&self.view, &mut attachments);
+        attachments
+    }
+}
+
+/// The topic's message followed by its replies, nested under what they replied to,
+/// as an html fragment.
+pub fn thread_html(topic: &DiscussionTopic, view: &TopicView) -> String {
+    let names = view.participants
+        .iter()
+        .map(|it| (it.id, it.display_name.as_str()))
+        .collect::<HashMap<_, _>>();
+    let mut html = String::new();
+    html.push_str(&format!("<p><strong>{}</strong>", escape(topic.author_name())));
+    if let Some(posted_at) = topic.posted_at.or(topic.created_at) {
+        html.push_str(&format!(" {}", format_time(posted_at)));
+    }
+    html.push_str("</p>\n");
+    html.push_str(topic.message.as_deref().unwrap_or_default());
+    html.push('\n');
+    if !view.view.is_empty() {
+        html.push_str("<h2>Replies</h2>\n");
+        entries_html(&view.view, &names, &mut html);
+    }
+    html
+}
+
+fn entries_html(entries: &[Entry], names: &HashMap<Id, &str>, html: &mut String) {
+    html.push_str("<ul>\n");
+    for entry in entries {
+        let author = entry.user_id
+            .and_then(|it| names.get(&it).copied())
+            .unwrap_or("unknown");
+        html.push_str(&format!("<li>\n<p><strong>{}</strong> {}</p>\n", escape(author), format_time(entry.created_at)));
+        match &entry.message {
+            Some(message) if !entry.deleted => html.push_str(message),
+            _ => html.push_str("<p><em>deleted</em></p>"),
+        }
+        html.push('\n');
+        if !entry.replies.is_empty() {
+            entries_html(&entry.replies, names, html);
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+}
+
+#[derive(Serialize)]
+struct AnnouncementsQuery {
+    per_page: u32,
+    #[serde(rename = "context_codes[]")]
+    context_codes: [String; 1],
+    start_date: DateTime<Utc>,
+    end_date: &'static str,
+}
+
+impl Course {
+    pub fn discussion_topics_endpoint(&self) -> String {
+        format!("courses/{}/discussion_topics", self.id)
+    }
+    
+    pub fn context_code(&self) -> String {
+        format!("course_{}", self.id)
+    }
+}
+
+impl CoreApi {
+    /// Not including announcements.
+    pub async fn discussion_topics(&self, course: &Course) -> Result<Vec<DiscussionTopic>, Box<dyn Error>> {
+        self.get_list(
+            course.discussion_topics_endpoint().as_str(),
+            &PerPage { per_page: 100 },
+        ).await
+    }
+    
+    /// Every announcement since the course was created.
+    pub async fn announcements(&self, course: &Course) -> Result<Vec<DiscussionTopic>, Box<dyn Error>> {
+        let context_code = course.context_code();
+        let announcements = self
+            .get_list::<_, DiscussionTopic>(
+                "announcements",
+                &AnnouncementsQuery {
+                    per_page: 100,
+                    context_codes: [context_code.clone()],
+                    // canvas only returns the last 14 days' without a start,
+                    // and the 28 days after the start without an end,
+                    // which is fixed so recorded syncs can be replayed any time
+                    start_date: course.created_at.with_timezone(&Utc),
+                    end_date: "9999-12-31T23:59:59Z",
+                },
+            )
+            .await?
+            .into_iter()
+            .filter(|it| it.context_code.as_deref().map_or(true, |code| code == context_code))
+            .collect();
+        Ok(announcements)
+    }
+    
+    pub async fn topic_view(&self, course: &Course, topic: &DiscussionTopic) -> Result<TopicView, Box<dyn Error>> {
+        self.get(
+            topic.view_endpoint(course).as_str(),
+            no_query(),
+        ).await
+    }
+}
@@ -0,0 +1,750 @@
+use crate::api::assignment;
+use crate::api::core::CoreApi;
+use crate::api::course;
+use crate::api::discussion;
+use crate::api::folder;
+use crate::api::module;
+use crate::api::page;
+use crate::api::retry::StatusError;
+use crate::api::submission;
+use crate::download::html;
+use crate::download::links;
+use crate::download::data::{Course, Directory, File, FileBase, FileTime, Id, IdName, Module, RegularFile, User};
+use crate::util::future::FutureIterator;
+use chrono::{DateTime, Local};
+use futures::future;
+use itertools::Itertools;
+use http_types::StatusCode;
+use optional::Optioned;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+// converts what the api returns into the download::data model
+
+// the ids of directories and files that aren't in canvas, e.x. Assignments or an assignment's description.
+// They're in the same directories as canvas's modules, files, etc., so they have the top bit set,
+// which canvas's ids never do, since they have to be exact in javascript.
+// The next bits are the kind of id, so e.x. attempt numbers can't collide with the fixed ids.
+const fn synthetic_id(kind: u32, n: u32) -> Id {
+    1 << 63 | (kind as Id) << 32 | n as Id
+}
+
+const FIXED: u32 = 0;
+const ATTEMPT: u32 = 1;
+
+// in a course's directory
+const FILES_ID: Id = synthetic_id(FIXED, 0);
+const ASSIGNMENTS_ID: Id = synthetic_id(FIXED, 1);
+const PAGES_ID: Id = synthetic_id(FIXED, 2);
+const DISCUSSIONS_ID: Id = synthetic_id(FIXED, 3);
+const ANNOUNCEMENTS_ID: Id = synthetic_id(FIXED, 4);
+// their names, which modules, also in a course's directory, can't have
+const COURSE_DIRECTORIES: &[&str] = &["Files", "Assignments", "Pages", "Discussions", "Announcements"];
+
+// in an assignment's directory
+const DESCRIPTION_ID: Id = synthetic_id(FIXED, 5);
+const METADATA_ID: Id = synthetic_id(FIXED, 6);
+const SUBMISSION_ID: Id = synthetic_id(FIXED, 7);
+
+// in a submission's directory, along with the attempts' directories
+const FEEDBACK_ID: Id = synthetic_id(FIXED, 8);
+
+// in an attempt's directory
+const SUBMITTED_TEXT_ID: Id = synthetic_id(FIXED, 9);
+
+// in a discussion's directory
+const THREAD_ID: Id = synthetic_id(FIXED, 10);
+
+// in the Announcements directory
+const ATTACHMENTS_ID: Id = synthetic_id(FIXED, 11);
+
+impl CoreApi {
+    pub async fn fetch_user(&self, id: IdName, created_at: DateTime<Local>) -> Result<User, Box<dyn Error>> {
+        let courses = self
+            .courses()
+            .await?
+            .into_iter()
+            .map(|course| self.fetch_course(course))
+            .join_all()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(User {
+            id,
+            created_at,
+            courses,
+        })
+    }
+    
+    async fn fetch_course(&self, course: course::Course) -> Result<Course, Box<dyn Error>> {
+        let ((modules, folder, assignments), (pages, discussions, announcements)) = future::join(
+            future::join3(
+                self.fetch_modules(&course),
+                self.fetch_files(&course),
+                self.fetch_assignments(&course),
+            ),
+            future::join3(
+                self.fetch_pages(&course),
+                self.fetch_discussions(&course),
+                self.fetch_announcements(&course),
+            ),
+        ).await;
+        let modules = modules?;
+        let folder = folder?;
+        let assignments = assignments?;
+        let pages = pages?;
+        let discussions = discussions?;
+        let announcements = announcements?;
+        let mut linked = Course {
+            id: IdName {
+                id: course.id,
+                name: file_name(&course.name),
+            },
+            created_at: course.created_at,
+            modules,
+            folder,
+            assignments,
+            pages,
+            discussions,
+            announcements,
+        };
+        self.link_files(&course, &mut linked).await?;
+        Ok(linked)
+    }
+    
+    /// Points the links in the course's documents at where the files they link to are synced,
+    /// first fetching any linked files that aren't synced anywhere else.
+    async fn link_files(&self, course: &course::Course, linked: &mut Course) -> Result<(), Box<dyn Error>> {
+        let mut paths = links::file_paths(linked);
+        let unsynced = links::unsynced_links(linked, &paths, &self.base_url)
+            .into_iter()
+            .map(|id| self.fetch_linked_file(course, id))
+            .join_all()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        links::add_unsynced(linked, &mut paths, unsynced);
+        links::rewrite_links(linked, &paths, &self.base_url);
+        Ok(())
+    }
+    
+    /// The course's Files tab, as a directory named Files.
+    async fn fetch_files(&self, course: &course::Course) -> Result<Directory, Box<dyn Error>> {
+        // the root folder's id isn't known if it's hidden, so the Files directory always has the same id
+        let root = course_directory(course, FILES_ID, "Files");
+        let folders = unless_hidden(self.folders(course).await)?;
+        // a hidden or locked root folder still has the rest of the folders under it,
+        // but none of its own files can be downloaded
+        let folders = folders
+            .into_iter()
+            .filter(|it| it.parent_folder_id.is_none() || it.is_visible())
+            .collect::<Vec<_>>();
+        let files = folders
+            .iter()
+            .map(|folder| async move {
+                if !folder.is_visible() {
+                    return Ok(Vec::new());
+                }
+                self.files(folder).await
+            })
+            .join_all()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut files_by_folder = HashMap::new();
+        let mut folders_by_parent = HashMap::<_, Vec<_>>::new();
+        for (folder, files) in folders.into_iter().zip(files) {
+            files_by_folder.insert(folder.id, files);
+            folders_by_parent
+                .entry(folder.parent_folder_id)
+                .or_default()
+                .push(folder);
+        }
+        let mut tree = FolderTree {
+            files_by_folder,
+            folders_by_parent,
+        };
+        let files = match tree.folders_by_parent.remove(&None).and_then(|mut it| it.pop()) {
+            None => Vec::new(),
+            Some(folder) => tree.contents(folder.id),
+        };
+        Ok(root.into_directory(files))
+    }
+    
+    /// The course's assignments, as a directory named Assignments,
+    /// with a directory for each assignment, named like announcements are.
+    async fn fetch_assignments(&self, course: &course::Course) -> Result<Directory, Box<dyn Error>> {
+        let root = course_directory(course, ASSIGNMENTS_ID, "Assignments");
+        let mut assignments = unless_hidden(self.assignments(course).await)?;
+        // oldest first, so an assignment's name doesn't change once a later one has the same name
+        assignments.sort_by_key(|it| it.id);
+        let mut names = Names::default();
+        let assignments = assignments
+            .into_iter()
+            .map(|assignment| {
+                let name = names.unique(file_name(&assignment.name), assignment.id);
+                self.fetch_assignment(course, assignment, name)
+            })
+            .join_all()
+            .await
+            .into_iter()
+            .map(|it| it.map(File::Directory))
+            .collect::<Result<_, _>>()?;
+        Ok(root.into_directory(assignments))
+    }
+    
+    /// An assignment's directory, named `name`, has its description, its metadata,
+    /// the files linked from its description, and the student's submission.
+    async fn fetch_assignment(&self, course: &course::Course, assignment: assignment::Assignment, name: String) -> Result<Directory, Box<dyn Error>> {
+        let description = assignment.description.as_deref().unwrap_or_default();
+        let linked_files = html::linked_file_ids(description, &self.base_url)
+            .into_iter()
+            .map(|id| self.fetch_linked_file(course, id))
+            .join_all();
+        let (linked_files, submission) = future::join(
+            linked_files,
+            self.fetch_submission(course, &assignment),
+        ).await;
+        let linked_files = linked_files
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        // the submission is extra, so the assignment is still exported without it
+        let submission = submission.unwrap_or_else(|e| {
+            eprintln!("skipped the submission for {}: {}", assignment.name, e);
+            None
+        });
+        let time = FileTime {
+            created_at: assignment.created_at,
+            updated_at: assignment.updated_at,
+            modified_at: None,
+        };
+        let generated = |id, name: &str| FileBase {
+            id: IdName {
+                id,
+                name: name.into(),
+            },
+            time: time.clone(),
+            size: Optioned::none(),
+        };
+        let format = self.document_format;
+        let description = generated(DESCRIPTION_ID, &format.file_name("description"))
+            .into_generated_file(format.content_type(), html::page(&assignment.name, description));
+        let metadata = serde_json::to_string_pretty(&assignment.metadata())?;
+        let metadata = generated(METADATA_ID, "assignment.json")
+            .into_generated_file("application/json", metadata);
+        let mut names = Names::taken(&[&description.base.id.name, &metadata.base.id.name, "submission"]);
+        let mut files = vec![
+            File::RegularFile(description),
+            File::RegularFile(metadata),
+        ];
+        files.extend(names
+            .rename_files(linked_files.into_iter().flatten())
+            .map(File::RegularFile));
+        files.extend(submission.map(File::Directory));
+        Ok(FileBase {
+            id: IdName {
+                id: assignment.id,
+                name,
+            },
+            time,
+            size: Optioned::none(),
+        }.into_directory(files))
+    }
+    
+    /// The student's submission, as a directory named submission,
+    /// with a document of its grade, rubric scores, and comments,
+    /// a directory for each attempt with what was submitted in it,
+    /// and the comments' attachments.
+    /// None if nothing's been submitted or graded yet, or the user isn't a student.
+    async fn fetch_submission(&self, course: &course::Course, assignment: &assignment::Assignment) -> Result<Option<Directory>, Box<dyn Error>> {
+        let submission = match self.submission(course, assignment).await {
+            Err(e) if is_hidden(e.as_ref()) => return Ok(None),
+            result => result?,
+        };
+        let attempts = submission.attempts();
+        if attempts.is_empty() && !submission.has_feedback() {
+            return Ok(None);
+        }
+        let format = self.document_format;
+        let time = FileTime {
+            created_at: attempts
+                .first()
+                .and_then(|it| it.submitted_at)
+                .unwrap_or(assignment.created_at),
+            updated_at: submission.updated_at(),
+            modified_at: None,
+        };
+        let feedback = FileBase {
+            id: IdName {
+                id: FEEDBACK_ID,
+                name: format.file_name("feedback"),
+            },
+            time: time.clone(),
+            size: Optioned::none(),
+        }.into_generated_file(
+            format.content_type(),
+            html::page(&assignment.name, &submission::feedback_html(assignment, &submission)),
+        );
+        let mut names = Names::taken(&[&feedback.base.id.name]);
+        let mut files = vec![File::RegularFile(feedback)];
+        files.extend(attempts
+            .iter()
+            .filter_map(|attempt| Some((attempt.attempt?, attempt)))
+            .map(|(number, attempt)| {
+                let time = FileTime::created_at(attempt.submitted_at.unwrap_or(time.created_at));
+                let mut attempt_files = Vec::new();
+                let mut attempt_names = Names::default();
+                if let Some(text) = attempt.html() {
+                    let text = FileBase {
+                        id: IdName {
+                            id: SUBMITTED_TEXT_ID,
+                            name: format.file_name("submission"),
+                        },
+                        time: time.clone(),
+                        size: Optioned::none(),
+                    }.into_generated_file(format.content_type(), html::page(&assignment.name, &text));
+                    attempt_names = Names::taken(&[&text.base.id.name]);
+                    attempt_files.push(File::RegularFile(text));
+                }
+                attempt_files.extend(attempt_names
+                    .rename_files(attempt.attachments
+                        .iter()
+                        .filter(|it| !it.locked_for_user)
+                        .map(|it| convert_file(it.clone())))
+                    .map(File::RegularFile));
+                FileBase {
+                    id: IdName {
+                        id: synthetic_id(ATTEMPT, number as u32),
+                        name: names.unique(format!("attempt {}", number), number),
+                    },
+                    time,
+                    size: Optioned::none(),
+                }.into_directory(attempt_files)
+            })
+            .map(File::Directory));
+        files.extend(names
+            .rename_files(submission.submission_comments
+                .iter()
+                .flat_map(|it| &it.attachments)
+                .filter(|it| !it.locked_for_user)
+                .unique_by(|it| it.id)
+                .map(|it| convert_file(it.clone())))
+            .map(File::RegularFile));
+        Ok(Some(FileBase {
+            id: IdName {
+                id: SUBMISSION_ID,
+                name: "submission".into(),
+            },
+            time,
+            size: Optioned::none(),
+        }.into_directory(files)))
+    }
+    
+    /// The course's wiki pages, as a directory named Pages,
+    /// with a document for each page the student can see, named like announcements are.
+    async fn fetch_pages(&self, course: &course::Course) -> Result<Directory, Box<dyn Error>> {
+        let root = course_directory(course, PAGES_ID, "Pages");
+        let mut pages = unless_hidden(self.pages(course).await)?;
+        // oldest first, so a page's name doesn't change once a later one has the same title
+        pages.sort_by_key(|it| it.page_id);
+        let format = self.document_format;
+        let mut names = Names::default();
+        let pages = pages
+            .into_iter()
+            .filter(|it| !it.locked_for_user)
+            .map(|page| {
+                let page::Page {
+                    page_id,
+                    title,
+                    created_at,
+                    updated_at,
+                    body,
+                    ..
+                } = page;
+                let contents = html::page(&title, body.as_deref().unwrap_or_default());
+                FileBase {
+                    id: IdName {
+                        id: page_id,
+                        name: format.file_name(&names.unique(file_name(&title), page_id)),
+                    },
+                    time: FileTime {
+                        created_at,
+                        updated_at,
+                        modified_at: None,
+                    },
+                    size: Optioned::none(),
+                }.into_generated_file(format.content_type(), contents)
+            })
+            .map(File::RegularFile)
+            .collect();
+        Ok(root.into_directory(pages))
+    }
+    
+    /// The course's discussions, as a directory named Discussions,
+    /// with a directory for each topic, named like announcements are.
+    async fn fetch_discussions(&self, course: &course::Course) -> Result<Directory, Box<dyn Error>> {
+        let root = course_directory(course, DISCUSSIONS_ID, "Discussions");
+        let mut topics = unless_hidden(self.discussion_topics(course).await)?;
+        // oldest first, so a topic's name doesn't change once a later one has the same title
+        topics.sort_by_key(|it| it.id);
+        let mut names = Names::default();
+        let topics = topics
+            .into_iter()
+            .map(|topic| {
+                let name = names.unique(file_name(&topic.title), topic.id);
+                self.fetch_discussion(course, topic, name)
+            })
+            .join_all()
+            .await
+            .into_iter()
+            .map(|it| it.map(File::Directory))
+            .collect::<Result<_, _>>()?;
+        Ok(root.into_directory(topics))
+    }
+    
+    /// A topic's directory, named `name`, has the whole thread as one document,
+    /// and the attachments of the topic and all its replies,
+    /// with their ids added if they'd be written over the thread or each other.
+    /// The thread is only exported again once there's a new reply.
+    async fn fetch_discussion(&self, course: &course::Course, topic: discussion::DiscussionTopic, name: String) -> Result<Directory, Box<dyn Error>> {
+        // e.x. the student has to post before seeing the replies
+        let view = unless_hidden(self.topic_view(course, &topic).await)?;
+        let time = FileTime {
+            created_at: topic.posted_at
+                .or(topic.created_at)
+                .unwrap_or(course.created_at),
+            updated_at: topic.last_reply_at,
+            modified_at: None,
+        };
+        let format = self.document_format;
+        let thread = FileBase {
+            id: IdName {
+                id: THREAD_ID,
+                name: format.file_name("discussion"),
+            },
+            time: time.clone(),
+            size: Optioned::none(),
+        }.into_generated_file(
+            format.content_type(),
+            html::page(&topic.title, &discussion::thread_html(&topic, &view)),
+        );
+        let mut names = Names::taken(&[&thread.base.id.name]);
+        let mut files = vec![File::RegularFile(thread)];
+        files.extend(names
+            .rename_files(topic.attachments
+                .iter()
+                .chain(view.attachments())
+                .filter(|it| !it.locked_for_user)
+                .unique_by(|it| it.id)
+                .map(|it| convert_file(it.clone())))
+            .map(File::RegularFile));
+        Ok(FileBase {
+            id: IdName {
+                id: topic.id,
+                name,
+            },
+            time,
+            size: Optioned::none(),
+        }.into_directory(files))
+    }
+    
+    /// The course's announcements, as a directory named Announcements,
+    /// with a document for each announcement, named by the date it was posted
+    /// and its title, and its id too if an earlier one has the same date and title,
+    /// and their attachments in an Attachments directory.
+    /// Announcements are only exported once, when they're new.
+    async fn fetch_announcements(&self, course: &course::Course) -> Result<Directory, Box<dyn Error>> {
+        let root = course_directory(course, ANNOUNCEMENTS_ID, "Announcements");
+        let mut announcements = unless_hidden(self.announcements(course).await)?;
+        // oldest first, so an announcement's name doesn't change once a later one has the same name
+        announcements.sort_by_key(|it| it.id);
+        let attachments = announcements
+            .iter()
+            .flat_map(|it| &it.attachments)
+            .filter(|it| !it.locked_for_user)
+            .unique_by(|it| it.id)
+            .map(|it| convert_file(it.clone()));
+        let attachments = Names::default()
+            .rename_files(attachments)
+            .map(File::RegularFile)
+            .collect();
+        let attachments = course_directory(course, ATTACHMENTS_ID, "Attachments").into_directory(attachments);
+        let format = self.document_format;
+        let mut files = vec![File::Directory(attachments)];
+        let mut names = Names::taken(&["Attachments"]);
+        files.extend(announcements
+            .into_iter()
+            .map(|announcement| {
+                let posted_at = announcement.posted_at
+                    .or(announcement.created_at)
+                    .unwrap_or(course.created_at);
+                let name = format!("{} {}", posted_at.format("%Y-%m-%d"), file_name(&announcement.title));
+                let name = names.unique(name, announcement.id);
+                let contents = html::page(
+                    &announcement.title,
+                    &discussion::thread_html(&announcement, &discussion::TopicView::default()),
+                );
+                FileBase {
+                    id: IdName {
+                        id: announcement.id,
+                        name: format.file_name(&name),
+                    },
+                    time: FileTime {
+                        created_at: posted_at,
+                        updated_at: None,
+                        modified_at: None,
+                    },
+                    size: Optioned::none(),
+                }.into_generated_file(format.content_type(), contents)
+            })
+            .map(File::RegularFile));
+        Ok(root.into_directory(files))
+    }
+    
+    /// The course's modules, named like announcements are,
+    /// and not like any of the other directories in the course's directory.
+    async fn fetch_modules(&self, course: &course::Course) -> Result<Vec<Module>, Box<dyn Error>> {
+        let mut modules = self.modules(course).await?;
+        // oldest first, so a module's name doesn't change once a later one has the same name
+        modules.sort_by_key(|it| it.id);
+        let mut names = Names::taken(COURSE_DIRECTORIES);
+        modules
+            .into_iter()
+            .map(|module| {
+                let name = names.unique(file_name(&module.name), module.id);
+                self.fetch_module(course, module, name)
+            })
+            .join_all()
+            .await
+            .into_iter()
+            .collect()
+    }
+    
+    /// A module's directory, named `name`, has exactly the files its items link to.
+    async fn fetch_module(&self, course: &course::Course, mut module: module::Module, name: String) -> Result<Module, Box<dyn Error>> {
+        let items = match module.items.take() {
+            Some(it) => it,
+            None => self.module_items(course, &module).await?,
+        };
+        let files = items
+            .iter()
+            .filter_map(module::ModuleItem::file_id)
+            .unique()
+            .map(|id| self.fetch_linked_file(course, id))
+            .join_all()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten();
+        // the files can be from different folders
+        let files = Names::default()
+            .rename_files(files)
+            .collect();
+        Ok(Self::convert_module(module, name, files, course.created_at))
+    }
+    
+    /// A file linked from a module, description, etc.
+    /// None if the student can't download it.
+    async fn fetch_linked_file(&self, course: &course::Course, id: Id) -> Result<Option<RegularFile>, Box<dyn Error>> {
+        match self.file(course, id).await {
+            Err(e) if is_hidden(e.as_ref()) => Ok(None),
+            Err(e) => Err(e),
+            Ok(file) => Ok(Some(file)
+                .filter(|it| !it.locked_for_user)
+                .map(convert_file)),
+        }
+    }
+    
+    fn convert_module(module: module::Module, name: String, files: Vec<RegularFile>, default_time: DateTime<Local>) -> Module {
+        let module::Module {
+            id,
+            name: _,
+            completed_at,
+            items: _,
+        } = module;
+        Module {
+            id: IdName {
+                id,
+                name,
+            },
+            completed_at: completed_at.unwrap_or(default_time),
+            files,
+        }
+    }
+}
+
+/// Students can't see the Files tab of some courses, or some of the files linked from modules.
+fn is_hidden(e: &(dyn Error + 'static)) -> bool {
+    match e.downcast_ref::<StatusError>() {
+        None => false,
+        Some(e) => match e.status {
+            StatusCode::Unauthorized | StatusCode::Forbidden | StatusCode::NotFound => true,
+            _ => false,
+        },
+    }
+}
+
+/// What canvas returned, or nothing if the student can't see it, e.x. a course's disabled Pages tab.
+fn unless_hidden<T: Default>(result: Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+    match result {
+        Err(e) if is_hidden(e.as_ref()) => Ok(T::default()),
+        result => result,
+    }
+}
+
+/// A directory that isn't in canvas, e.x. Pages, so it has one of the fixed ids,
+/// and is as old as the course.
+fn course_directory(course: &course::Course, id: Id, name: &str) -> FileBase {
+    FileBase::directory(
+        IdName {
+            id,
+            name: name.into(),
+        },
+        course.created_at,
+    )
+}
+
+/// `courses/:id/folders` lists every folder flat, so this puts them back into a tree.
+struct FolderTree {
+    files_by_folder: HashMap<Id, Vec<folder::File>>,
+    folders_by_parent: HashMap<Option<Id>, Vec<folder::Folder>>,
+}
+
+impl FolderTree {
+    fn contents(&mut self, id: Id) -> Vec<File> {
+        let folders = self.folders_by_parent
+            .remove(&Some(id))
+            .unwrap_or_default();
+        let files = self.files_by_folder
+            .remove(&id)
+            .unwrap_or_default();
+        let mut contents = Vec::with_capacity(folders.len() + files.len());
+        for folder in folders {
+            let directory = self.convert_folder(folder);
+            contents.push(File::Directory(directory));
+        }
+        contents.extend(files
+            .into_iter()
+            .filter(|it| !it.locked_for_user)
+            .map(convert_file)
+            .map(File::RegularFile));
+        contents
+    }
+    
+    fn convert_folder(&mut self, folder: folder::Folder) -> Directory {
+        let files = self.contents(folder.id);
+        let folder::Folder {
+            id,
+            name,
+            created_at,
+            updated_at,
+            ..
+        } = folder;
+        FileBase {
+            id: IdName {
+                id,
+                name: file_name(&name),
+            },
+            time: FileTime {
+                created_at,
+                updated_at,
+                modified_at: None,
+            },
+            size: Optioned::none(),
+        }.into_directory(files)
+    }
+}
+
+/// A name from canvas, e.x. an assignment's, as a file name.
+/// Canvas allows any name, e.x. "HW 1/2", but a file name can't have a slash or be . or ..
+fn file_name(name: &str) -> String {
+    let name = name.replace(|c: char| c == '/' || c == '\0', "_");
+    if name.is_empty() || name == "." || name == ".." {
+        return "_".repeat(name.len().max(1));
+    }
+    name
+}
+
+/// The names of what's in a directory, so what canvas names the same isn't written over each other.
+/// Case is ignored, since those names are the same on some file systems.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    /// With `names` already taken, e.x. by the documents generated in the directory.
+    fn taken(names: &[&str]) -> Self {
+        Self(names
+            .iter()
+            .map(|it| it.to_lowercase())
+            .collect())
+    }
+    
+    /// `name`, with `id` added if it's taken.
+    /// Everything should be named oldest first, so a name doesn't change once something newer has the same one.
+    fn unique(&mut self, name: String, id: Id) -> String {
+        self.unique_with(name, |name| format!("{} ({})", name, id))
+    }
+    
+    /// Like `unique`, but `id` is added before the extension, e.x. notes (123).txt
+    fn unique_file(&mut self, name: String, id: Id) -> String {
+        self.unique_with(name, |name| match name.rfind('.') {
+            Some(i) if i > 0 => format!("{} ({}){}", &name[..i], id, &name[i..]),
+            _ => format!("{} ({})", name, id),
+        })
+    }
+    
+    fn unique_with(&mut self, name: String, with_id: impl FnOnce(&str) -> String) -> String {
+        if self.0.insert(name.to_lowercase()) {
+            return name;
+        }
+        let name = with_id(&name);
+        self.0.insert(name.to_lowercase());
+        name
+    }
+    
+    /// Renames the `files` whose names are taken, oldest first.
+    fn rename_files<'a>(&'a mut self, files: impl IntoIterator<Item = RegularFile>) -> impl Iterator<Item = RegularFile> + 'a {
+        files
+            .into_iter()
+            .sorted_by_key(|it| it.base.id.id)
+            .map(move |mut file| {
+                let name = std::mem::take(&mut file.base.id.name);
+                file.base.id.name = self.unique_file(name, file.base.id.id);
+                file
+            })
+    }
+}
+
+fn convert_file(file: folder::File) -> RegularFile {
+    let folder::File {
+        id,
+        display_name,
+        content_type,
+        size,
+        created_at,
+        updated_at,
+        modified_at,
+        ..
+    } = file;
+    RegularFile {
+        base: FileBase {
+            id: IdName {
+                id,
+                // e.x. a reply's attachment, named by another student
+                name: file_name(&display_name),
+            },
+            time: FileTime {
+                created_at,
+                updated_at,
+                modified_at,
+            },
+            size: size.into(),
+        },
+        content_type,
+        contents: None,
+    }
+}
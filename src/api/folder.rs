@@ -0,0 +1,76 @@
+use crate::api::core::{no_query, CoreApi, PerPage};
+use crate::api::course::Course;
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct Folder {
+    pub id: Id,
+    pub name: String,
+    // None for the course's root folder
+    pub parent_folder_id: Option<Id>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub hidden_for_user: bool,
+    #[serde(default)]
+    pub locked_for_user: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct File {
+    pub id: Id,
+    /// The name shown in canvas, `filename` is url encoded.
+    pub display_name: String,
+    #[serde(rename = "content-type")]
+    pub content_type: Option<String>,
+    pub size: Option<u64>,
+    pub created_at: DateTime<Local>,
+    pub updated_at: Option<DateTime<Local>>,
+    pub modified_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub locked_for_user: bool,
+}
+
+impl Course {
+    pub fn folders_endpoint(&self) -> String {
+        format!("courses/{}/folders", self.id)
+    }
+}
+
+impl Folder {
+    pub fn files_endpoint(&self) -> String {
+        format!("folders/{}/files", self.id)
+    }
+    
+    /// Hidden and locked folders can't be downloaded from.
+    pub fn is_visible(&self) -> bool {
+        !self.hidden_for_user && !self.locked_for_user
+    }
+}
+
+impl CoreApi {
+    /// Every folder in the course, not just the top level ones.
+    pub async fn folders(&self, course: &Course) -> Result<Vec<Folder>, Box<dyn Error>> {
+        self.get_list(
+            course.folders_endpoint().as_str(),
+            &PerPage { per_page: 100 },
+        ).await
+    }
+    
+    pub async fn files(&self, folder: &Folder) -> Result<Vec<File>, Box<dyn Error>> {
+        self.get_list(
+            folder.files_endpoint().as_str(),
+            &PerPage { per_page: 100 },
+        ).await
+    }
+    
+    pub async fn file(&self, course: &Course, id: Id) -> Result<File, Box<dyn Error>> {
+        self.get(
+            format!("courses/{}/files/{}", course.id, id).as_str(),
+            no_query(),
+        ).await
+    }
+}
@@ -0,0 +1,158 @@
+// canvas throttles each access token with a leaky bucket,
+// reporting what's left of it in X-Rate-Limit-Remaining and what a request took out in X-Request-Cost,
+// and answering 403 Forbidden (Rate Limit Exceeded) once it's empty
+
+use async_std::task;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Canvas's bucket holds 700, so requests are only spread out once it's less than half full.
+const LOW_WATER: f64 = 300.0;
+
+/// How long a request waits when the estimated budget is completely gone.
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Before canvas has reported a cost, assume requests are about this expensive.
+const DEFAULT_COST: f64 = 10.0;
+
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub const MAX_THROTTLED_RETRIES: u32 = 8;
+
+struct State {
+    remaining: Option<f64>,
+    cost: f64,
+    in_flight: u32,
+    paused_until: Option<Instant>,
+}
+
+impl State {
+    /// Requests already sent will each take about `cost` out of what's left.
+    fn estimated_remaining(&self) -> Option<f64> {
+        self.remaining
+            .map(|it| it - self.in_flight as f64 * self.cost)
+    }
+    
+    fn paused_for(&self, now: Instant) -> Option<Duration> {
+        self.paused_until
+            .filter(|&it| it > now)
+            .map(|it| it - now)
+    }
+    
+    fn delay(&self, now: Instant) -> Duration {
+        let budget = match self.estimated_remaining() {
+            Some(remaining) if remaining < LOW_WATER => {
+                let emptiness = ((LOW_WATER - remaining) / LOW_WATER).min(1.0);
+                MAX_DELAY.mul_f64(emptiness)
+            }
+            _ => Duration::from_secs(0),
+        };
+        self.paused_for(now)
+            .unwrap_or_default()
+            .max(budget)
+    }
+}
+
+/// Paces every request made with one access token.
+pub struct Governor {
+    state: Mutex<State>,
+}
+
+/// Held while a request is in flight.
+pub struct Permit<'a> {
+    governor: &'a Governor,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.governor.lock().in_flight -= 1;
+    }
+}
+
+// keyed by canvas domain and Authorization header, since CoreApis are cloned and deserialized all over
+static GOVERNORS: Mutex<Option<HashMap<String, Arc<Governor>>>> = Mutex::new(None);
+
+impl Governor {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                remaining: None,
+                cost: DEFAULT_COST,
+                in_flight: 0,
+                paused_until: None,
+            }),
+        }
+    }
+    
+    /// The governor shared by every request with this token to this canvas.
+    pub fn for_token(domain: &str, authorization: &str) -> Arc<Self> {
+        GOVERNORS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .entry(format!("{} {}", domain, authorization))
+            .or_insert_with(|| Arc::new(Self::new()))
+            .clone()
+    }
+    
+    fn lock(&self) -> MutexGuard<State> {
+        self.state.lock().unwrap()
+    }
+    
+    /// Waits until a request can be sent,
+    /// longer the closer the bucket is estimated to be to running out.
+    pub async fn acquire(&self) -> Permit<'_> {
+        let delay = self.lock().delay(Instant::now());
+        if delay > Duration::from_secs(0) {
+            task::sleep(delay).await;
+        }
+        // another request may have been throttled in the meantime
+        loop {
+            let paused = self.lock().paused_for(Instant::now());
+            match paused {
+                None => break,
+                Some(it) => task::sleep(it).await,
+            }
+        }
+        self.lock().in_flight += 1;
+        Permit { governor: self }
+    }
+    
+    /// Records the X-Rate-Limit-Remaining and X-Request-Cost of a response.
+    pub fn update(&self, remaining: Option<f64>, cost: Option<f64>) {
+        let mut state = self.lock();
+        if remaining.is_some() {
+            state.remaining = remaining;
+        }
+        if let Some(cost) = cost {
+            state.cost = cost;
+        }
+    }
+    
+    /// Pauses every request for the larger of `retry_after` and a jittered exponential backoff.
+    pub fn throttled(&self, retry_after: Option<Duration>, attempt: u32) {
+        let backoff = BASE_BACKOFF
+            .checked_mul(1 << attempt.min(16))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        // somewhere in the upper half, so concurrent requests don't all retry at once
+        let backoff = backoff.mul_f64(0.5 + jitter() / 2.0);
+        let pause = retry_after
+            .unwrap_or_default()
+            .max(backoff);
+        let until = Instant::now() + pause;
+        let mut state = self.lock();
+        state.paused_until = Some(state.paused_until.map_or(until, |it| it.max(until)));
+    }
+}
+
+/// Uniform in [0, 1), random enough for spreading out retries.
+pub(super) fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
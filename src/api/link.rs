@@ -1,7 +1,10 @@
+use url::Url;
+
 #[derive(Debug, PartialEq, Eq)]
 pub(super) enum LinkType {
     Current,
     Next,
+    Prev,
     First,
     Last,
 }
@@ -11,6 +14,7 @@ impl LinkType {
         Some(match link_type {
             "current" => LinkType::Current,
             "next" => LinkType::Next,
+            "prev" => LinkType::Prev,
             "first" => LinkType::First,
             "last" => LinkType::Last,
             _ => return None,
@@ -63,7 +67,8 @@ pub(super) struct Links<'a> {
 
 impl<'a> Links<'a> {
     pub(super) fn of(raw: &'a str) -> Option<Links<'a>> {
-        let mut links: Vec<Link<'a>> = raw.split(',').map(Link::of).collect::<Option<Vec<_>>>()?;
+        // skip links we don't understand instead of losing all of them
+        let mut links: Vec<Link<'a>> = raw.split(',').filter_map(Link::of).collect();
         let current = links
             .iter()
             .find(|it| it.type_ == LinkType::Current)
@@ -79,6 +84,37 @@ impl<'a> Links<'a> {
     pub fn iter(&self) -> impl Iterator<Item = &Link<'a>> {
         self.links.iter()
     }
+    
+    pub fn url(&self, type_: LinkType) -> Option<&'a str> {
+        self.links
+            .iter()
+            .find(|it| it.type_ == type_)
+            .map(|it| it.url)
+    }
+}
+
+/// The page number in a link's url.
+/// None if it's not a number, like canvas's opaque "bookmark:..." pages.
+pub(super) fn page_number(url: &str) -> Option<u64> {
+    let url = Url::parse(url).ok()?;
+    let (_, page) = url
+        .query_pairs()
+        .find(|(key, _)| key == "page")?;
+    page.parse().ok()
+}
+
+pub(super) fn with_page_number(url: &str, page: u64) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    let pairs = url
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", &page.to_string());
+    Some(url.into_string())
 }
 
 impl Default for Links<'_> {
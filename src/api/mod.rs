@@ -7,12 +7,24 @@
 
 pub(crate) mod core;
 mod link;
+mod governor;
+pub mod retry;
+pub mod transport;
+pub mod account;
+pub mod user;
+pub mod course;
+pub mod module;
+pub mod folder;
+pub mod assignment;
+pub mod submission;
+pub mod page;
+pub mod discussion;
+mod fetch;
+pub mod query;
+pub mod connection;
 
 pub use self::core::CoreApi;
 
-// mod course;
-// mod module;
-// mod query;
 
 // pub struct Api {
 //     api: CoreApi,
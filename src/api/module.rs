@@ -1,24 +1,96 @@
-use crate::api::core::Id;
-use serde::Deserialize;
+use crate::api::core::{CoreApi, PerPage};
+use crate::api::course::Course;
+use crate::api::folder;
+use crate::api::query::modules::{ModulesCourseModulesConnectionNodes, ModulesCourseModulesConnectionNodesModuleItemsContentOn};
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
 
 #[derive(Debug, Deserialize)]
 pub struct Module {
     pub id: Id,
     pub name: String,
-    pub items: Vec<ModuleItem>,
+    // only set for students
+    pub completed_at: Option<DateTime<Local>>,
+    // only included when asked for, and even then left out if there are too many
+    pub items: Option<Vec<ModuleItem>>,
 }
 
 impl Module {
-    //    pub fn items_endpoint(&self) -> String {
-    //
-    //    }
+    pub fn items_endpoint(&self, course: &Course) -> String {
+        format!("{}/{}/items", course.modules_endpoint(), self.id)
+    }
 }
 
+#[derive(Debug, Deserialize)]
 pub struct ModuleItem {
     pub id: Id,
-    pub url: String,
+    pub title: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    // the id of the file, page, etc.
+    pub content_id: Option<Id>,
+    pub url: Option<String>,
 }
 
-pub struct File {
-    pub id: Id,
+impl ModuleItem {
+    pub fn file_id(&self) -> Option<Id> {
+        self.content_id
+            .filter(|_| self.kind.as_deref() == Some("File"))
+    }
+}
+
+#[derive(Serialize)]
+struct ModulesQuery {
+    per_page: u32,
+    #[serde(rename = "include[]")]
+    include: &'static [&'static str],
+}
+
+impl CoreApi {
+    /// Includes the modules' items, unless there are too many.
+    pub async fn modules(&self, course: &Course) -> Result<Vec<Module>, Box<dyn Error>> {
+        self.get_list(
+            course.modules_endpoint().as_str(),
+            &ModulesQuery {
+                per_page: 100,
+                include: &["items"],
+            },
+        ).await
+    }
+    
+    /// For modules that had too many items to include.
+    pub async fn module_items(&self, course: &Course, module: &Module) -> Result<Vec<ModuleItem>, Box<dyn Error>> {
+        self.get_list(
+            module.items_endpoint(course).as_str(),
+            &PerPage { per_page: 100 },
+        ).await
+    }
+}
+
+impl ModulesCourseModulesConnectionNodes {
+    /// The `... on File` contents of the module's items,
+    /// with `default_time` for any missing timestamps.
+    /// The sync doesn't use this, it gets modules' items from REST's `modules?include[]=items`.
+    pub fn files(&self, default_time: DateTime<Local>) -> Vec<folder::File> {
+        self.module_items
+            .iter()
+            .flatten()
+            .filter_map(|item| match &item.content.as_ref()?.on {
+                ModulesCourseModulesConnectionNodesModuleItemsContentOn::File(file) => Some(file),
+                _ => None,
+            })
+            .filter_map(|file| Some(folder::File {
+                id: file.id.parse().ok()?,
+                display_name: file.display_name.clone()?,
+                content_type: file.content_type.clone(),
+                size: None,
+                created_at: file.created_at.unwrap_or(default_time),
+                updated_at: file.updated_at,
+                modified_at: None,
+                locked_for_user: false,
+            }))
+            .collect()
+    }
 }
@@ -0,0 +1,46 @@
+use crate::api::core::CoreApi;
+use crate::api::course::Course;
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct Page {
+    pub page_id: Id,
+    /// The page's slug, which is what identifies it in urls.
+    pub url: String,
+    pub title: String,
+    pub created_at: DateTime<Local>,
+    pub updated_at: Option<DateTime<Local>>,
+    /// Html, only included when asked for, and left out if it's locked.
+    pub body: Option<String>,
+    #[serde(default)]
+    pub locked_for_user: bool,
+}
+
+#[derive(Serialize)]
+struct PagesQuery {
+    per_page: u32,
+    #[serde(rename = "include[]")]
+    include: &'static [&'static str],
+}
+
+impl Course {
+    pub fn pages_endpoint(&self) -> String {
+        format!("courses/{}/pages", self.id)
+    }
+}
+
+impl CoreApi {
+    /// Includes the pages' bodies.
+    pub async fn pages(&self, course: &Course) -> Result<Vec<Page>, Box<dyn Error>> {
+        self.get_list(
+            course.pages_endpoint().as_str(),
+            &PagesQuery {
+                per_page: 100,
+                include: &["body"],
+            },
+        ).await
+    }
+}
@@ -1,10 +1,16 @@
+// custom scalars used by the modules below
+
+/// an ISO8601 formatted time string
+pub type DateTime = chrono::DateTime<chrono::Local>;
+pub type URL = String;
+
 pub struct Courses;
 
 pub mod courses {
     #![allow(dead_code)]
 
     pub const OPERATION_NAME: &'static str = "Courses";
-    pub const QUERY: &'static str = "query Courses {\n    allCourses {\n        _id\n        name\n    }\n}\n\nquery Modules($course_id: ID!) {\n    course(id: $course_id) {\n        id\n        name\n        modulesConnection {\n            nodes {\n                _id\n                name\n                moduleItems {\n                    _id\n                    url\n                    content {\n                        __typename\n                        ... on File {\n                            _id\n                            displayName\n                            contentType\n                            createdAt\n                            updatedAt\n                        }\n                    }\n                }\n            }\n            pageInfo {\n                hasNextPage\n            }\n        }\n    }\n}\n";
+    pub const QUERY: &'static str = "query Courses {\n    allCourses {\n        _id\n        name\n        createdAt\n        updatedAt\n    }\n}\n\nquery Modules($course_id: ID!, $after: String) {\n    course(id: $course_id) {\n        id\n        name\n        modulesConnection(after: $after) {\n            nodes {\n                _id\n                name\n                createdAt\n                updatedAt\n                moduleItems {\n                    _id\n                    url\n                    content {\n                        __typename\n                        ... on File {\n                            _id\n                            displayName\n                            contentType\n                            createdAt\n                            updatedAt\n                        }\n                    }\n                }\n            }\n            pageInfo {\n                hasNextPage\n                endCursor\n            }\n        }\n    }\n}\n";
 
     use serde::{Deserialize, Serialize};
 
@@ -16,6 +22,8 @@ pub mod courses {
     type Int = i64;
     #[allow(dead_code)]
     type ID = String;
+    #[doc = "an ISO8601 formatted time string"]
+    type DateTime = super::DateTime;
 
     #[derive(Deserialize)]
     pub struct CoursesAllCourses {
@@ -23,6 +31,10 @@ pub mod courses {
         #[serde(rename = "_id")]
         pub id: ID,
         pub name: String,
+        #[serde(rename = "createdAt")]
+        pub created_at: Option<DateTime>,
+        #[serde(rename = "updatedAt")]
+        pub updated_at: Option<DateTime>,
     }
 
     #[derive(Serialize)]
@@ -54,7 +66,7 @@ pub mod modules {
     #![allow(dead_code)]
 
     pub const OPERATION_NAME: &'static str = "Modules";
-    pub const QUERY: &'static str = "query Courses {\n    allCourses {\n        _id\n        name\n    }\n}\n\nquery Modules($course_id: ID!) {\n    course(id: $course_id) {\n        id\n        name\n        modulesConnection {\n            nodes {\n                _id\n                name\n                moduleItems {\n                    _id\n                    url\n                    content {\n                        __typename\n                        ... on File {\n                            _id\n                            displayName\n                            contentType\n                            createdAt\n                            updatedAt\n                        }\n                    }\n                }\n            }\n            pageInfo {\n                hasNextPage\n            }\n        }\n    }\n}\n";
+    pub const QUERY: &'static str = "query Courses {\n    allCourses {\n        _id\n        name\n        createdAt\n        updatedAt\n    }\n}\n\nquery Modules($course_id: ID!, $after: String) {\n    course(id: $course_id) {\n        id\n        name\n        modulesConnection(after: $after) {\n            nodes {\n                _id\n                name\n                createdAt\n                updatedAt\n                moduleItems {\n                    _id\n                    url\n                    content {\n                        __typename\n                        ... on File {\n                            _id\n                            displayName\n                            contentType\n                            createdAt\n                            updatedAt\n                        }\n                    }\n                }\n            }\n            pageInfo {\n                hasNextPage\n                endCursor\n            }\n        }\n    }\n}\n";
 
     use serde::{Deserialize, Serialize};
 
@@ -120,6 +132,10 @@ pub mod modules {
         #[serde(rename = "_id")]
         pub id: ID,
         pub name: Option<String>,
+        #[serde(rename = "createdAt")]
+        pub created_at: Option<DateTime>,
+        #[serde(rename = "updatedAt")]
+        pub updated_at: Option<DateTime>,
         #[serde(rename = "moduleItems")]
         pub module_items: Option<Vec<ModulesCourseModulesConnectionNodesModuleItems>>,
     }
@@ -130,6 +146,9 @@ pub mod modules {
         #[doc = "When paginating forwards, are there more items?"]
         #[serde(rename = "hasNextPage")]
         pub has_next_page: Boolean,
+        #[doc = "When paginating forwards, the cursor to continue."]
+        #[serde(rename = "endCursor")]
+        pub end_cursor: Option<String>,
     }
 
     #[derive(Deserialize)]
@@ -153,6 +172,7 @@ pub mod modules {
     #[derive(Serialize)]
     pub struct Variables {
         pub course_id: ID,
+        pub after: Option<String>,
     }
 
     impl Variables {}
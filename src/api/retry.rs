@@ -0,0 +1,129 @@
+use crate::api::core::CoreApi;
+use crate::api::governor::jitter;
+use crate::api::transport::TransportError;
+use async_std::task;
+use http_types::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+/// A response with a status that wasn't a success.
+#[derive(Debug)]
+pub struct StatusError {
+    pub method: &'static str,
+    pub url: String,
+    pub status: StatusCode,
+}
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {} failed: {}", self.method, self.url, self.status)
+    }
+}
+
+impl Error for StatusError {}
+
+/// Which failures of idempotent requests, i.e. GETs and downloads, are retried and how.
+/// Set in sync.json's settings.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Including the first, so 1 means never retry.
+    pub max_attempts: u32,
+    /// Doubled after every retry, up to `max_backoff_ms`.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Response statuses to retry.
+    pub statuses: Vec<u16>,
+    /// `std::io::ErrorKind`s to retry, by name, e.x. ConnectionReset.
+    pub io_errors: Vec<String>,
+    /// Whether to retry other failures to get a response, e.x. failing to connect.
+    pub connection_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            statuses: vec![500, 502, 503, 504],
+            io_errors: [
+                "ConnectionReset",
+                "ConnectionAborted",
+                "ConnectionRefused",
+                "BrokenPipe",
+                "TimedOut",
+                "UnexpectedEof",
+                "Interrupted",
+            ]
+                .iter()
+                .map(|&it| it.to_owned())
+                .collect(),
+            connection_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn retries_io(&self, e: &io::Error) -> bool {
+        let kind = format!("{:?}", e.kind());
+        self.io_errors.contains(&kind)
+    }
+    
+    pub fn is_retryable(&self, e: &(dyn Error + 'static)) -> bool {
+        if let Some(e) = e.downcast_ref::<StatusError>() {
+            return self.statuses.contains(&(e.status as u16));
+        }
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            return self.retries_io(e);
+        }
+        if let Some(e) = e.downcast_ref::<TransportError>() {
+            return match e.io_error() {
+                Some(e) => self.retries_io(e),
+                None => self.connection_errors,
+            };
+        }
+        false
+    }
+    
+    /// Jittered, so concurrent requests that failed together don't all retry together.
+    fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self.initial_backoff_ms
+            .saturating_mul(1u64 << retry.min(32))
+            .min(self.max_backoff_ms);
+        Duration::from_millis(backoff).mul_f64(0.5 + jitter() / 2.0)
+    }
+}
+
+impl CoreApi {
+    /// Runs `attempt` until it succeeds, fails in a way the retry policy doesn't retry,
+    /// or runs out of attempts, logging every retry of `what`.
+    /// Only for idempotent requests.
+    pub(crate) async fn retry<T, F, Fut>(&self, what: &str, mut attempt: F) -> Result<T, Box<dyn Error>>
+        where
+            F: FnMut() -> Fut,
+            Fut: Future<Output = Result<T, Box<dyn Error>>>, {
+        let policy = &self.retry_policy;
+        let mut attempts = 1;
+        loop {
+            let e = match attempt().await {
+                Ok(it) => return Ok(it),
+                Err(e) => e,
+            };
+            if attempts >= policy.max_attempts || !policy.is_retryable(e.as_ref()) {
+                return Err(e);
+            }
+            let backoff = policy.backoff(attempts - 1);
+            eprintln!(
+                "retrying {} in {:.1}s ({} of {} attempts failed): {}",
+                what, backoff.as_secs_f64(), attempts, policy.max_attempts, e,
+            );
+            task::sleep(backoff).await;
+            attempts += 1;
+        }
+    }
+}
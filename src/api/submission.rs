@@ -0,0 +1,184 @@
+use crate::api::assignment::Assignment;
+use crate::api::core::CoreApi;
+use crate::api::course::Course;
+use crate::api::folder;
+use crate::download::html::{escape, format_time};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// What was submitted in one attempt.
+#[derive(Debug, Deserialize)]
+pub struct Attempt {
+    /// None if nothing's been submitted.
+    pub attempt: Option<u64>,
+    pub submitted_at: Option<DateTime<Local>>,
+    pub submission_type: Option<String>,
+    /// Html, for online_text_entry.
+    pub body: Option<String>,
+    /// For online_url.
+    pub url: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<folder::File>,
+}
+
+impl Attempt {
+    /// The text or url submitted, as an html fragment, if it was.
+    pub fn html(&self) -> Option<String> {
+        let mut html = self.body.clone().unwrap_or_default();
+        if let Some(url) = &self.url {
+            html.push_str(&format!("\n<p><a href=\"{url}\">{url}</a></p>", url = escape(url)));
+        }
+        Some(html).filter(|it| !it.is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmissionComment {
+    pub author_name: Option<String>,
+    /// Plain text.
+    pub comment: String,
+    pub created_at: DateTime<Local>,
+    #[serde(default)]
+    pub attachments: Vec<folder::File>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RubricScore {
+    pub points: Option<f64>,
+    pub rating_id: Option<String>,
+    pub comments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Submission {
+    /// The latest attempt.
+    #[serde(flatten)]
+    pub latest: Attempt,
+    pub grade: Option<String>,
+    pub score: Option<f64>,
+    pub graded_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub late: bool,
+    #[serde(default)]
+    pub submission_comments: Vec<SubmissionComment>,
+    /// By rubric criterion id.
+    pub rubric_assessment: Option<HashMap<String, RubricScore>>,
+    /// Every attempt, including the latest.
+    #[serde(default)]
+    pub submission_history: Vec<Attempt>,
+}
+
+impl Submission {
+    /// Only the attempts where something was submitted, oldest first.
+    pub fn attempts(&self) -> Vec<&Attempt> {
+        let mut attempts = if self.submission_history.is_empty() {
+            vec![&self.latest]
+        } else {
+            self.submission_history.iter().collect()
+        };
+        attempts.retain(|it| it.attempt.is_some());
+        attempts.sort_by_key(|it| it.attempt);
+        attempts
+    }
+    
+    pub fn has_feedback(&self) -> bool {
+        self.grade.is_some()
+            || !self.submission_comments.is_empty()
+            || self.rubric_assessment.as_ref().map_or(false, |it| !it.is_empty())
+    }
+    
+    /// When it was last submitted, graded, or commented on.
+    pub fn updated_at(&self) -> Option<DateTime<Local>> {
+        self.attempts()
+            .into_iter()
+            .filter_map(|it| it.submitted_at)
+            .chain(self.graded_at)
+            .chain(self.submission_comments.iter().map(|it| it.created_at))
+            .max()
+    }
+}
+
+/// The submission's grade, rubric scores, and comments, as an html fragment.
+pub fn feedback_html(assignment: &Assignment, submission: &Submission) -> String {
+    let mut html = String::new();
+    if let Some(grade) = &submission.grade {
+        html.push_str(&format!("<p>Grade: {}", escape(grade)));
+        if let (Some(score), Some(points)) = (submission.score, assignment.points_possible) {
+            html.push_str(&format!(" ({} / {})", score, points));
+        }
+        html.push_str("</p>\n");
+    }
+    if submission.late {
+        html.push_str("<p>Late</p>\n");
+    }
+    let scores = submission.rubric_assessment
+        .as_ref()
+        .filter(|it| !it.is_empty());
+    if let Some(scores) = scores {
+        html.push_str("<h2>Rubric</h2>\n<table>\n");
+        html.push_str("<tr><th>Criterion</th><th>Rating</th><th>Points</th><th>Comments</th></tr>\n");
+        for criterion in &assignment.rubric {
+            let score = match scores.get(&criterion.id) {
+                None => continue,
+                Some(it) => it,
+            };
+            let rating = criterion.ratings
+                .iter()
+                .find(|it| Some(&it.id) == score.rating_id.as_ref())
+                .and_then(|it| it.description.as_deref())
+                .unwrap_or_default();
+            let points = match (score.points, criterion.points) {
+                (Some(points), Some(max)) => format!("{} / {}", points, max),
+                (Some(points), None) => points.to_string(),
+                (None, _) => String::new(),
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(criterion.description.as_deref().unwrap_or_default()),
+                escape(rating),
+                points,
+                escape(score.comments.as_deref().unwrap_or_default()),
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    if !submission.submission_comments.is_empty() {
+        html.push_str("<h2>Comments</h2>\n<ul>\n");
+        for comment in &submission.submission_comments {
+            let author = comment.author_name.as_deref().unwrap_or("unknown");
+            let text = escape(&comment.comment).replace('\n', "<br>\n");
+            html.push_str(&format!(
+                "<li>\n<p><strong>{}</strong> {}</p>\n<p>{}</p>\n</li>\n",
+                escape(author), format_time(comment.created_at), text,
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+#[derive(Serialize)]
+struct SubmissionQuery {
+    #[serde(rename = "include[]")]
+    include: &'static [&'static str],
+}
+
+impl Assignment {
+    pub fn submission_endpoint(&self, course: &Course) -> String {
+        format!("{}/{}/submissions/self", course.assignments_endpoint(), self.id)
+    }
+}
+
+impl CoreApi {
+    /// The user's own submission, with every attempt, its comments, and its rubric assessment.
+    pub async fn submission(&self, course: &Course, assignment: &Assignment) -> Result<Submission, Box<dyn Error>> {
+        self.get(
+            assignment.submission_endpoint(course).as_str(),
+            &SubmissionQuery {
+                include: &["submission_comments", "rubric_assessment", "submission_history"],
+            },
+        ).await
+    }
+}
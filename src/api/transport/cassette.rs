@@ -0,0 +1,118 @@
+use crate::api::transport::{BufferedResponse, Exchange, HttpRequest, Method};
+use http_types::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::path::Path;
+
+/// What access tokens are replaced with in a cassette.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Text bodies are kept readable so cassettes can be inspected and edited by hand.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Body {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    fn new(bytes: Vec<u8>, redact: impl Fn(&str) -> String) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Body::Text(redact(&text)),
+            Err(e) => Body::Bytes(e.into_bytes()),
+        }
+    }
+    
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Body::Text(it) => it.into_bytes(),
+            Body::Bytes(it) => it,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    method: String,
+    url: String,
+    request_body: Body,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Body,
+}
+
+/// Recorded canvas traffic, saved as json so a sync can be replayed offline.
+#[derive(Serialize, Deserialize)]
+pub struct Cassette {
+    recordings: Vec<Recording>,
+}
+
+impl Cassette {
+    /// Every one of `secrets` is redacted wherever it appears,
+    /// and Authorization headers aren't kept at all.
+    pub fn new(exchanges: Vec<Exchange>, secrets: &[String]) -> Self {
+        let redact = |s: &str| {
+            secrets
+                .iter()
+                .filter(|it| !it.is_empty())
+                .fold(s.to_owned(), |s, secret| s.replace(secret.as_str(), REDACTED))
+        };
+        let recordings = exchanges
+            .into_iter()
+            .map(|Exchange { request, response }| Recording {
+                method: request.method.to_string(),
+                url: redact(&request.url),
+                request_body: Body::new(request.body, redact),
+                status: response.status.into(),
+                headers: response.headers
+                    .into_iter()
+                    .map(|(name, value)| (name, redact(&value)))
+                    .collect(),
+                body: Body::new(response.body, redact),
+            })
+            .collect();
+        Self { recordings }
+    }
+    
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("couldn't read cassette {}: {}", path.display(), e))?;
+        let this = serde_json::from_slice(&bytes)?;
+        Ok(this)
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let bytes = serde_json::to_vec_pretty(self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+    
+    pub fn into_exchanges(self) -> Result<Vec<Exchange>, Box<dyn Error>> {
+        self.recordings
+            .into_iter()
+            .map(|recording| -> Result<Exchange, Box<dyn Error>> {
+                let method = match recording.method.as_str() {
+                    "GET" => Method::Get,
+                    "POST" => Method::Post,
+                    method => return Err(format!("unknown method {} in cassette", method).into()),
+                };
+                let status = StatusCode::try_from(recording.status)
+                    .map_err(|_| format!("unknown status {} in cassette", recording.status))?;
+                Ok(Exchange {
+                    request: HttpRequest {
+                        method,
+                        url: recording.url,
+                        headers: Vec::new(),
+                        body: recording.request_body.into_bytes(),
+                    },
+                    response: BufferedResponse {
+                        status,
+                        headers: recording.headers,
+                        body: recording.body.into_bytes(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
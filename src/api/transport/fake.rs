@@ -0,0 +1,63 @@
+use crate::api::transport::{BufferedResponse, HttpRequest, HttpResponse, Method, Transport, TransportError};
+use futures::future::{self, BoxFuture};
+use http_types::StatusCode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Answers requests from responses set up in memory, without any network.
+/// Requests match by method and url, or by url without the query if there's no exact match.
+/// Anything else gets a 404.
+#[derive(Default)]
+pub struct FakeTransport {
+    responses: Mutex<HashMap<(Method, String), BufferedResponse>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn respond(&self, method: Method, url: &str, response: BufferedResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert((method, url.to_owned()), response);
+    }
+    
+    pub fn respond_json(&self, method: Method, url: &str, json: &impl Serialize) {
+        self.respond(method, url, BufferedResponse {
+            status: StatusCode::Ok,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: serde_json::to_vec(json).unwrap(),
+        });
+    }
+    
+    /// Every request so far, in order.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+    
+    fn response(&self, request: &HttpRequest) -> HttpResponse {
+        let responses = self.responses.lock().unwrap();
+        let without_query = request.url.split('?').next().unwrap_or_default();
+        responses
+            .get(&(request.method, request.url.clone()))
+            .or_else(|| responses.get(&(request.method, without_query.to_owned())))
+            .map(BufferedResponse::to_response)
+            .unwrap_or_else(|| BufferedResponse {
+                status: StatusCode::NotFound,
+                headers: Vec::new(),
+                body: Vec::new(),
+            }.to_response())
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        let response = self.response(&request);
+        self.requests.lock().unwrap().push(request);
+        Box::pin(future::ready(Ok(response)))
+    }
+}
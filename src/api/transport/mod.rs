@@ -0,0 +1,172 @@
+// how CoreApi actually sends requests,
+// so tests and offline debugging can swap surf out
+
+use futures::future::BoxFuture;
+use futures::io::{AsyncRead, AsyncReadExt, Cursor};
+use http_types::StatusCode;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+mod surf;
+mod fake;
+mod record;
+mod cassette;
+
+pub use self::cassette::{Cassette, REDACTED};
+pub use self::fake::FakeTransport;
+pub use self::record::{Exchange, RecordingTransport, ReplayTransport};
+pub use self::surf::SurfTransport;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let method = match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        };
+        f.write_str(method)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    pub method: Method,
+    /// Including the query.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: String) -> Self {
+        Self {
+            method,
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+    
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(it, _)| it.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// A response whose body is streamed, e.x. straight into a file.
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    body: Box<dyn AsyncRead + Send + Unpin>,
+}
+
+impl HttpResponse {
+    pub fn new(status: StatusCode, headers: Vec<(String, String)>, body: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        Self {
+            status,
+            headers,
+            body: Box::new(body),
+        }
+    }
+    
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+    
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+    
+    pub async fn body_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.body.read_to_end(&mut bytes).await?;
+        Ok(bytes)
+    }
+    
+    pub async fn body_string(&mut self) -> io::Result<String> {
+        let bytes = self.body_bytes().await?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+    
+    pub async fn body_json<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        let bytes = self.body_bytes().await?;
+        serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl AsyncRead for HttpResponse {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.body).poll_read(cx, buf)
+    }
+}
+
+/// A response with the whole body read, so it can be kept around and answered again.
+#[derive(Clone, Debug)]
+pub struct BufferedResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl BufferedResponse {
+    pub async fn read(mut response: HttpResponse) -> io::Result<Self> {
+        let body = response.body_bytes().await?;
+        Ok(Self {
+            status: response.status,
+            headers: response.headers,
+            body,
+        })
+    }
+    
+    pub fn to_response(&self) -> HttpResponse {
+        HttpResponse::new(self.status, self.headers.clone(), Cursor::new(self.body.clone()))
+    }
+}
+
+/// The request couldn't be sent or no response came back, e.x. the connection dropped.
+#[derive(Debug)]
+pub struct TransportError(pub Box<dyn Error + Send + Sync>);
+
+impl TransportError {
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.0.downcast_ref()
+    }
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for TransportError {}
+
+impl From<io::Error> for TransportError {
+    fn from(e: io::Error) -> Self {
+        Self(Box::new(e))
+    }
+}
+
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>>;
+}
+
+pub fn default() -> Arc<dyn Transport> {
+    Arc::new(SurfTransport)
+}
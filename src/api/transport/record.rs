@@ -0,0 +1,99 @@
+use crate::api::transport::{BufferedResponse, HttpRequest, HttpResponse, Transport, TransportError};
+use futures::future::{self, BoxFuture};
+use std::sync::{Arc, Mutex};
+
+/// A request and the response it got.
+#[derive(Clone, Debug)]
+pub struct Exchange {
+    pub request: HttpRequest,
+    pub response: BufferedResponse,
+}
+
+impl Exchange {
+    fn answers(&self, request: &HttpRequest) -> bool {
+        // not the headers, so a different access token still matches
+        self.request.method == request.method
+            && self.request.url == request.url
+            && self.request.body == request.body
+    }
+}
+
+/// Sends requests with another transport, keeping every exchange so it can be replayed.
+/// Responses are read whole before they're returned.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        Self {
+            inner,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+    
+    /// Every exchange so far, in the order the responses came back.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+    
+    async fn record(&self, request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let response = self.inner.send(request.clone()).await?;
+        let response = BufferedResponse::read(response).await?;
+        let replayed = response.to_response();
+        self.exchanges
+            .lock()
+            .unwrap()
+            .push(Exchange { request, response });
+        Ok(replayed)
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(self.record(request))
+    }
+}
+
+/// Answers requests with recorded exchanges, without any network.
+/// Identical requests get their recorded responses in order,
+/// and the last one again once they run out, e.x. for retries.
+pub struct ReplayTransport {
+    exchanges: Vec<Exchange>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayTransport {
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        let used = vec![false; exchanges.len()];
+        Self {
+            exchanges,
+            used: Mutex::new(used),
+        }
+    }
+    
+    fn replay(&self, request: &HttpRequest) -> Result<HttpResponse, TransportError> {
+        let mut used = self.used.lock().unwrap();
+        let matching = self.exchanges
+            .iter()
+            .enumerate()
+            .filter(|(_, it)| it.answers(request))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let i = matching
+            .iter()
+            .copied()
+            .find(|&i| !used[i])
+            .or_else(|| matching.last().copied())
+            .ok_or_else(|| TransportError(format!("no recorded response for {} {}", request.method, request.url).into()))?;
+        used[i] = true;
+        Ok(self.exchanges[i].response.to_response())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(future::ready(self.replay(&request)))
+    }
+}
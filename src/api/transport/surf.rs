@@ -0,0 +1,64 @@
+use crate::api::transport::{HttpRequest, HttpResponse, Method, Transport, TransportError};
+use futures::future::BoxFuture;
+use http_types::headers::HeaderName;
+use std::io;
+use url::Url;
+
+/// The headers CoreApi reads,
+/// since surf responses can only be asked for a header by name.
+const RESPONSE_HEADERS: &[&str] = &[
+    "Content-Type",
+    "Link",
+    "Retry-After",
+    "X-Rate-Limit-Remaining",
+    "X-Request-Cost",
+];
+
+fn header_name(name: &str) -> HeaderName {
+    name.parse().unwrap()
+}
+
+fn transport_error(e: http_types::Error) -> TransportError {
+    // keep io errors visible to the retry policy
+    match e.downcast::<io::Error>() {
+        Ok(e) => e.into(),
+        Err(e) => TransportError(e.into()),
+    }
+}
+
+/// Sends requests over the network with surf.
+pub struct SurfTransport;
+
+impl SurfTransport {
+    async fn send_surf(request: HttpRequest) -> Result<HttpResponse, TransportError> {
+        let url = Url::parse(&request.url).map_err(|e| TransportError(e.into()))?;
+        let method = match request.method {
+            Method::Get => http_types::Method::Get,
+            Method::Post => http_types::Method::Post,
+        };
+        let mut req = ::surf::Request::new(method, url);
+        // before the headers, since it sets the Content-Type
+        if !request.body.is_empty() {
+            req = req.body_bytes(&request.body);
+        }
+        for (name, value) in &request.headers {
+            req = req.set_header(header_name(name), value);
+        }
+        let resp = req.await.map_err(transport_error)?;
+        let headers = RESPONSE_HEADERS
+            .iter()
+            .filter_map(|&name| {
+                resp.header(&header_name(name))
+                    .and_then(|it| it.first()) // TODO see how surf parses multi value headers
+                    .map(|value| (name.to_owned(), value.as_str().to_owned()))
+            })
+            .collect();
+        Ok(HttpResponse::new(resp.status(), headers, resp))
+    }
+}
+
+impl Transport for SurfTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, TransportError>> {
+        Box::pin(Self::send_surf(request))
+    }
+}
@@ -0,0 +1,28 @@
+use crate::api::core::{CoreApi, no_query};
+use crate::download::data::{Id, IdName};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct SelfUser {
+    pub id: Id,
+    pub name: String,
+    // only visible to admins
+    pub created_at: Option<DateTime<Local>>,
+}
+
+impl SelfUser {
+    pub fn id_name(&self) -> IdName {
+        IdName {
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl CoreApi {
+    pub async fn current_user(&self) -> Result<SelfUser, Box<dyn Error>> {
+        self.get("users/self", no_query()).await
+    }
+}
@@ -0,0 +1,367 @@
+// a small skim-like picker for when `sk` isn't installed
+// type to filter, up/down (or ctrl-p/ctrl-n) to move, enter to select, ctrl-c to cancel
+
+use std::error::Error;
+use std::io;
+use std::io::{BufRead, Write};
+
+const MAX_SHOWN: usize = 10;
+
+// lower is better, None if `query` isn't a subsequence of `item`
+fn score(query: &str, item: &str) -> Option<usize> {
+    let item = item.to_lowercase();
+    let mut item_chars = item.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for q in query.to_lowercase().chars() {
+        let (i, _) = item_chars.find(|&(_, c)| c == q)?;
+        first.get_or_insert(i);
+        last = i;
+    }
+    // prefer tight matches that start early
+    Some(first.map(|first| (last - first) + first).unwrap_or(0))
+}
+
+fn filter(query: &str, items: &[String]) -> Vec<usize> {
+    let mut matches = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| score(query, item).map(|score| (score, i)))
+        .collect::<Vec<_>>();
+    // stable, so ties keep their original order
+    matches.sort_by_key(|&(score, _)| score);
+    matches
+        .into_iter()
+        .map(|(_, i)| i)
+        .collect()
+}
+
+enum Key {
+    Char(char),
+    Backspace,
+    ClearLine,
+    Up,
+    Down,
+    Enter,
+    Cancel,
+}
+
+struct Picker<'a> {
+    items: &'a [String],
+    query: String,
+    matches: Vec<usize>,
+    selected: usize,
+    width: usize,
+}
+
+impl<'a> Picker<'a> {
+    fn new(items: &'a [String], width: usize) -> Self {
+        Self {
+            items,
+            query: String::new(),
+            matches: filter("", items),
+            selected: 0,
+            width,
+        }
+    }
+    
+    fn refilter(&mut self) {
+        self.matches = filter(&self.query, self.items);
+        self.selected = 0;
+    }
+    
+    /// Returns Some once the picker is done, with the index of the chosen item if there is one.
+    fn press(&mut self, key: Key) -> Option<Option<usize>> {
+        match key {
+            Key::Char(c) => {
+                self.query.push(c);
+                self.refilter();
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.refilter();
+            }
+            Key::ClearLine => {
+                self.query.clear();
+                self.refilter();
+            }
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => {
+                if self.selected + 1 < self.matches.len().min(MAX_SHOWN) {
+                    self.selected += 1;
+                }
+            }
+            Key::Enter => return self.matches
+                .get(self.selected)
+                .map(|&i| Some(i)),
+            Key::Cancel => return Some(None),
+        }
+        None
+    }
+    
+    fn truncate<'s>(&self, s: &'s str) -> &'s str {
+        let max = self.width.saturating_sub(3);
+        s.char_indices()
+            .nth(max)
+            .map(|(i, _)| &s[..i])
+            .unwrap_or(s)
+    }
+    
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        // clear everything below the prompt and redraw it
+        write!(out, "\r\x1b[J> {}", self.query)?;
+        let shown = self.matches.len().min(MAX_SHOWN);
+        for (row, &i) in self.matches[..shown].iter().enumerate() {
+            let item = self.truncate(&self.items[i]);
+            if row == self.selected {
+                write!(out, "\r\n\x1b[7m> {}\x1b[0m", item)?;
+            } else {
+                write!(out, "\r\n  {}", item)?;
+            }
+        }
+        write!(out, "\r\n  {}/{}", self.matches.len(), self.items.len())?;
+        // move back up to the end of the prompt
+        write!(out, "\x1b[{}A\r\x1b[{}C", shown + 1, 2 + self.query.chars().count())?;
+        out.flush()
+    }
+    
+    fn clear(out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\r\x1b[J")?;
+        out.flush()
+    }
+}
+
+#[cfg(unix)]
+mod tty {
+    use super::Key;
+    use std::fs::File;
+    use std::io;
+    use std::io::Read;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    
+    /// Turns off line buffering, echo, and signals until dropped.
+    pub struct RawMode {
+        fd: RawFd,
+        original: libc::termios,
+    }
+    
+    impl RawMode {
+        pub fn enable(tty: &File) -> io::Result<Self> {
+            let fd = tty.as_raw_fd();
+            let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+            if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let original = termios;
+            // ctrl-c is handled as a key so the terminal is always restored
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { fd, original })
+        }
+    }
+    
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &self.original) };
+        }
+    }
+    
+    pub fn width(tty: &File) -> usize {
+        let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+        let result = unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+        if result != 0 || size.ws_col == 0 {
+            return 80;
+        }
+        size.ws_col as usize
+    }
+    
+    fn read_byte(tty: &mut File) -> io::Result<u8> {
+        let mut byte = [0];
+        tty.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+    
+    pub fn read_key(tty: &mut File) -> io::Result<Option<Key>> {
+        let key = match read_byte(tty)? {
+            3 | 7 => Key::Cancel, // ctrl-c, ctrl-g
+            b'\r' | b'\n' => Key::Enter,
+            8 | 127 => Key::Backspace,
+            21 => Key::ClearLine, // ctrl-u
+            16 => Key::Up, // ctrl-p
+            14 => Key::Down, // ctrl-n
+            27 => {
+                // only arrow keys are handled, other escape sequences are dropped
+                if read_byte(tty)? != b'[' {
+                    return Ok(None);
+                }
+                match read_byte(tty)? {
+                    b'A' => Key::Up,
+                    b'B' => Key::Down,
+                    _ => return Ok(None),
+                }
+            }
+            byte if byte < b' ' => return Ok(None),
+            byte if byte < 0x80 => Key::Char(byte as char),
+            first => {
+                // the length of a utf-8 char is the number of leading 1s
+                let len = (!first).leading_zeros() as usize;
+                let mut bytes = vec![first];
+                for _ in 1..len {
+                    bytes.push(read_byte(tty)?);
+                }
+                match std::str::from_utf8(&bytes).ok().and_then(|it| it.chars().next()) {
+                    Some(c) => Key::Char(c),
+                    None => return Ok(None),
+                }
+            }
+        };
+        Ok(Some(key))
+    }
+}
+
+#[cfg(unix)]
+fn select_in_tty(items: &[String]) -> Result<Option<usize>, Box<dyn Error>> {
+    // like skim, use the tty directly so stdin and stdout can still be redirected
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+    let mut out = tty.try_clone()?;
+    let _raw = tty::RawMode::enable(&tty)?;
+    let mut picker = Picker::new(items, tty::width(&tty));
+    let chosen = loop {
+        picker.draw(&mut out)?;
+        let key = match tty::read_key(&mut tty)? {
+            None => continue,
+            Some(key) => key,
+        };
+        if let Some(chosen) = picker.press(key) {
+            break chosen;
+        }
+    };
+    Picker::clear(&mut out)?;
+    Ok(chosen)
+}
+
+#[cfg(not(unix))]
+fn select_in_tty(_items: &[String]) -> Result<Option<usize>, Box<dyn Error>> {
+    Err("no tty".into())
+}
+
+// for when there's no tty to draw in
+fn select_by_number(items: &[String]) -> Result<Option<usize>, Box<dyn Error>> {
+    let stderr = io::stderr();
+    let mut out = stderr.lock();
+    for (i, item) in items.iter().enumerate() {
+        writeln!(out, "{:>3}) {}", i + 1, item)?;
+    }
+    write!(out, "select [1-{}]: ", items.len())?;
+    out.flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let chosen = line
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|&n| n >= 1 && n <= items.len())
+        .map(|n| n - 1);
+    Ok(chosen)
+}
+
+/// Returns the index of the chosen item.
+pub fn select(items: &[String]) -> Result<usize, Box<dyn Error>> {
+    let chosen = match select_in_tty(items) {
+        Ok(chosen) => chosen,
+        Err(_) => select_by_number(items)?,
+    };
+    let chosen = chosen.ok_or("nothing selected")?;
+    Ok(chosen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter, score, Key, Picker};
+    
+    fn items() -> Vec<String> {
+        vec![
+            "Stony Brook College".to_owned(),
+            "Columbia University".to_owned(),
+            "Cornell University".to_owned(),
+        ]
+    }
+    
+    #[test]
+    fn score_needs_a_subsequence() {
+        assert_eq!(score("", "Columbia"), Some(0));
+        assert_eq!(score("clmb", "Columbia"), Some(5));
+        assert_eq!(score("COL", "columbia"), Some(2));
+        assert_eq!(score("bc", "Columbia"), None);
+        assert_eq!(score("columbias", "Columbia"), None);
+    }
+    
+    #[test]
+    fn score_prefers_tight_early_matches() {
+        assert!(score("col", "Columbia") < score("col", "Stony Brook College"));
+        assert!(score("cu", "Cu Boulder") < score("cu", "Columbia University"));
+    }
+    
+    #[test]
+    fn filter_sorts_by_score_keeping_ties_in_order() {
+        let items = items();
+        assert_eq!(filter("", &items), vec![0, 1, 2]);
+        assert_eq!(filter("col", &items), vec![1, 2, 0]);
+        assert_eq!(filter("c", &items), vec![1, 2, 0]);
+        assert_eq!(filter("university", &items), vec![2, 1]);
+        assert!(filter("harvard", &items).is_empty());
+    }
+    
+    #[test]
+    fn typing_filters_and_selects_the_best_match() {
+        let items = items();
+        let mut picker = Picker::new(&items, 80);
+        picker.press(Key::Down);
+        assert_eq!(picker.selected, 1);
+        for c in "cor".chars() {
+            assert_eq!(picker.press(Key::Char(c)), None);
+        }
+        assert_eq!(picker.matches, vec![2, 1]);
+        assert_eq!(picker.selected, 0);
+        assert_eq!(picker.press(Key::Enter), Some(Some(2)));
+    }
+    
+    #[test]
+    fn moving_stays_within_the_matches() {
+        let items = items();
+        let mut picker = Picker::new(&items, 80);
+        picker.press(Key::Up);
+        assert_eq!(picker.selected, 0);
+        for _ in 0..5 {
+            picker.press(Key::Down);
+        }
+        assert_eq!(picker.selected, 2);
+        assert_eq!(picker.press(Key::Enter), Some(Some(2)));
+    }
+    
+    #[test]
+    fn editing_the_query_refilters() {
+        let items = items();
+        let mut picker = Picker::new(&items, 80);
+        for c in "cox".chars() {
+            picker.press(Key::Char(c));
+        }
+        assert!(picker.matches.is_empty());
+        // nothing to choose, so the picker keeps going
+        assert_eq!(picker.press(Key::Enter), None);
+        picker.press(Key::Backspace);
+        assert_eq!(picker.query, "co");
+        assert_eq!(picker.matches, vec![1, 2, 0]);
+        picker.press(Key::ClearLine);
+        assert_eq!(picker.query, "");
+        assert_eq!(picker.matches, vec![0, 1, 2]);
+        assert_eq!(picker.press(Key::Cancel), Some(None));
+    }
+}
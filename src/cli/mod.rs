@@ -1,4 +1,5 @@
 mod select;
+mod fuzzy;
 
 use std::path::PathBuf;
 use std::ffi::OsStr;
@@ -7,8 +8,10 @@ use std::fmt;
 use structopt::StructOpt;
 use itertools::Itertools;
 use canvas_file_sync::CanvasFileSync;
-use crate::cli::select::select_canvas_using_skim;
+use canvas_file_sync::state::Credentials;
+use crate::cli::select::select_canvas;
 use std::error::Error;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct CanvasParentDir(PathBuf);
@@ -45,8 +48,24 @@ impl Display for CanvasParentDir {
 
 #[derive(StructOpt, Debug)]
 pub struct AddUser {
+    /// Only needed to add a user, not to search with --json
     #[structopt(long, env = "CANVAS_ACCESS_TOKEN", hide_env_values = true)]
-    access_token: String,
+    access_token: Option<String>,
+    /// Only consider the canvas at this domain, e.x. courseworks2.columbia.edu
+    #[structopt(long)]
+    domain: Option<String>,
+    /// Don't search, use the canvas at this url (e.x. http://localhost:3000), named by the search terms
+    #[structopt(long, conflicts_with = "domain")]
+    base_url: Option<String>,
+    /// Select the Nth of several search results instead of asking
+    #[structopt(long)]
+    pick: Option<usize>,
+    /// Never ask, and fail listing the candidates if the search is ambiguous
+    #[structopt(long)]
+    no_input: bool,
+    /// Print the search results as JSON without adding anything
+    #[structopt(long)]
+    json: bool,
     search: Vec<String>,
 }
 
@@ -65,6 +84,18 @@ pub struct Args {
     dir: CanvasParentDir,
     #[structopt(long)]
     skip_git: bool,
+    /// Where to store access tokens [default: <config dir>/canvas-file-sync/credentials.json]
+    #[structopt(long, env = "CANVAS_CREDENTIALS", parse(from_os_str))]
+    credentials: Option<PathBuf>,
+    /// Seconds to wait for another sync to finish before giving up (waits forever by default)
+    #[structopt(long, env = "CANVAS_LOCK_TIMEOUT")]
+    lock_timeout: Option<u64>,
+    /// Record the sync's canvas traffic into this cassette file, with access tokens redacted
+    #[structopt(long, parse(from_os_str))]
+    record: Option<PathBuf>,
+    /// Sync offline from a cassette recorded with --record
+    #[structopt(long, parse(from_os_str), conflicts_with = "record")]
+    replay: Option<PathBuf>,
     #[structopt(subcommand)]
     command: Option<Command>,
 }
@@ -73,12 +104,37 @@ impl From<AddUser> for canvas_file_sync::AddUser {
     fn from(it: AddUser) -> Self {
         let AddUser {
             access_token,
+            domain,
+            base_url,
             search,
+            ..
         } = it;
         let search = search.into_iter().join(" ");
         Self {
-            access_token,
+            access_token: access_token.unwrap_or_default(),
             search,
+            domain,
+            base_url,
+        }
+    }
+}
+
+impl AddUser {
+    fn run(self, api: &CanvasFileSync) -> Result<(), Box<dyn Error>> {
+        let json = self.json;
+        let pick = self.pick;
+        let no_input = self.no_input;
+        let add_user: canvas_file_sync::AddUser = self.into();
+        if json {
+            return select::print_json(&CanvasFileSync::search(&add_user)?);
+        }
+        if add_user.access_token.is_empty() {
+            return Err("adding a user needs an access token, use --access-token or CANVAS_ACCESS_TOKEN".into());
+        }
+        match (pick, no_input) {
+            (Some(n), _) => api.add_user(add_user, select::select_nth(n)),
+            (None, true) => api.add_user(add_user, select::select_none),
+            (None, false) => api.add_user(add_user, select_canvas),
         }
     }
 }
@@ -88,16 +144,30 @@ impl Args {
         let Self {
             dir,
             skip_git,
+            credentials,
+            lock_timeout,
+            record,
+            replay,
             command,
         } = self;
+        if let (Some(Command::Add(_)), true) = (&command, record.is_some() || replay.is_some()) {
+            return Err("--record and --replay are only for syncing, not add".into());
+        }
         let dir = dir.into_canvas_dir();
+        let credentials = credentials
+            .or_else(Credentials::default_path)
+            .ok_or("no config dir for credentials, use --credentials")?;
         let api = CanvasFileSync {
             dir,
             skip_git,
+            lock_timeout: lock_timeout.map(Duration::from_secs),
+            credentials,
+            record,
+            replay,
         };
         match command {
             Some(Command::Add(add_user)) =>
-                api.add_user(add_user.into(), select_canvas_using_skim)?,
+                add_user.run(&api)?,
             None =>
                 api.sync()?,
         }
@@ -7,8 +7,10 @@ use std::fmt::Display;
 use serde::export::Formatter;
 use std::fmt;
 use std::convert::{TryFrom, TryInto};
-use canvas_file_sync::api::CoreApi;
+use crate::cli::fuzzy;
+use serde::Serialize;
 
+#[derive(Serialize)]
 struct DisplayCanvas {
     name: String,
     domain: String,
@@ -16,7 +18,7 @@ struct DisplayCanvas {
 
 impl PartialEq<CanvasBase> for DisplayCanvas {
     fn eq(&self, other: &CanvasBase) -> bool {
-        self.name == other.id.name && self.name == other.api.domain
+        self.name == other.id.name && self.domain == other.api.domain()
     }
 }
 
@@ -27,14 +29,20 @@ impl From<CanvasBase> for DisplayCanvas {
                 id: _,
                 name,
             },
-            api: CoreApi {
-                domain,
-                authorization: _,
-            },
+            api,
         } = canvas;
         Self {
             name,
-            domain,
+            domain: api.domain(),
+        }
+    }
+}
+
+impl DisplayCanvas {
+    fn of(canvas: &CanvasBase) -> Self {
+        Self {
+            name: canvas.id.name.clone(),
+            domain: canvas.api.domain(),
         }
     }
 }
@@ -53,40 +61,152 @@ impl TryFrom<&str> for DisplayCanvas {
     type Error = &'static str;
     
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut split = value.split(SEP);
-        let name = split.next().ok_or("no name")?.into();
+        // split at the last SEP, since names may contain it but domains can't
+        let mut split = value.rsplitn(2, SEP);
         let domain = split.next().ok_or("no domain")?.into();
+        let name = split.next().ok_or("no name")?.into();
         let this = Self {name, domain};
         Ok(this)
     }
 }
 
+fn skim_installed() -> bool {
+    Command::new("sk")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Uses skim if it's installed, otherwise a built-in picker,
+/// unless there's only one canvas to choose.
+pub fn select_canvas(mut canvases: Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
+    if canvases.len() == 1 {
+        return Ok(canvases.pop().unwrap());
+    }
+    if skim_installed() {
+        select_canvas_using_skim(canvases)
+    } else {
+        select_canvas_in_process(canvases)
+    }
+}
+
+/// What's shown for each canvas, which [`DisplayCanvas::try_from`] reads back.
+fn items(canvases: &[CanvasBase]) -> Vec<String> {
+    canvases.iter()
+        .map(|it| DisplayCanvas::of(it).to_string())
+        .collect()
+}
+
+pub fn select_canvas_in_process(mut canvases: Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
+    let items = items(&canvases);
+    let chosen = fuzzy::select(&items)?;
+    Ok(canvases.swap_remove(chosen))
+}
+
 pub fn select_canvas_using_skim(canvases: Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
-    let input = canvases.iter()
-        .map(|it| format!("{}", it))
-        .join("\0");
+    let input = items(&canvases).join("\0");
     let mut child = Command::new("sk")
         .args(&["--read0", "--print0", "--no-multi"])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()?;
-    // TODO handle when skim is not installed
     child.stdin
         .as_mut()
         .ok_or("skim has no stdin")?
         .write_all(input.as_bytes())?;
     let output = child.wait_with_output()?;
     if !output.status.success() {
-        let error_message = std::str::from_utf8(&*output.stderr)?;
+        let error_message = std::str::from_utf8(&*output.stderr)?.trim();
+        // skim exits non-zero without saying anything when cancelled with esc
+        if error_message.is_empty() {
+            return Err("nothing selected".into());
+        }
         return Err(error_message.into());
     }
-    let chosen: DisplayCanvas = std::str::from_utf8(&*output.stdout)?
+    let chosen = std::str::from_utf8(&*output.stdout)?
         .split('\0')
-        .next().unwrap()
-        .try_into()?;
+        .next().unwrap();
+    if chosen.is_empty() {
+        return Err("nothing selected".into());
+    }
+    let chosen: DisplayCanvas = chosen.try_into()?;
     let chosen = canvases.into_iter()
         .find(|it| chosen == *it)
-        .ok_or("nothing printed by skim")?;
+        .ok_or("skim printed an unknown canvas")?;
     Ok(chosen)
 }
+
+fn candidates_error(message: &str, canvases: &[CanvasBase]) -> Box<dyn Error> {
+    let candidates = canvases.iter()
+        .enumerate()
+        .map(|(i, it)| format!("{:>3}) {}", i + 1, DisplayCanvas::of(it)))
+        .join("\n");
+    format!("{}:\n{}", message, candidates).into()
+}
+
+/// For when there's no one to ask, so only a single canvas can be chosen.
+pub fn select_none(mut canvases: Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
+    if canvases.len() == 1 {
+        return Ok(canvases.pop().unwrap());
+    }
+    Err(candidates_error("multiple canvases found, choose one with --domain or --pick", &canvases))
+}
+
+/// Selects the 1-based `n`th canvas.
+pub fn select_nth(n: usize) -> impl FnOnce(Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
+    move |mut canvases| {
+        if n == 0 || n > canvases.len() {
+            let message = format!("--pick {} is out of range", n);
+            return Err(candidates_error(&message, &canvases));
+        }
+        Ok(canvases.swap_remove(n - 1))
+    }
+}
+
+pub fn print_json(canvases: &[CanvasBase]) -> Result<(), Box<dyn Error>> {
+    let canvases = canvases.iter()
+        .map(DisplayCanvas::of)
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(std::io::stdout(), &canvases)?;
+    println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{items, DisplayCanvas};
+    use canvas_file_sync::api::CoreApi;
+    use canvas_file_sync::download::data::{CanvasBase, IdName};
+    use std::convert::TryFrom;
+    
+    fn canvas(domain: &str, name: &str) -> CanvasBase {
+        let base_url = CoreApi::parse_base_url(domain).unwrap();
+        CanvasBase {
+            api: CoreApi::new(base_url, "token".into()),
+            id: IdName {
+                id: 1,
+                name: name.into(),
+            },
+        }
+    }
+    
+    #[test]
+    fn items_read_back_as_their_canvas() {
+        let canvases = vec![
+            canvas("canvas.columbia.edu", "Columbia University"),
+            canvas("canvas.example.edu", "Arts & Sciences"),
+        ];
+        for (item, canvas) in items(&canvases).iter().zip(&canvases) {
+            let chosen = DisplayCanvas::try_from(item.as_str()).unwrap();
+            assert!(chosen == *canvas, "{} didn't read back", item);
+        }
+    }
+    
+    #[test]
+    fn items_without_a_domain_are_rejected() {
+        assert!(DisplayCanvas::try_from("Columbia University").is_err());
+    }
+}
@@ -20,27 +20,31 @@ pub struct CanvasBase {
 }
 
 pub struct Canvas {
-    base: CanvasBase,
-    users: Vec<User>,
+    pub(crate) base: CanvasBase,
+    pub(crate) users: Vec<User>,
 }
 
 pub struct User {
-    id: IdName,
-    created_at: DateTime<Local>,
-    courses: Vec<Course>,
+    pub(crate) id: IdName,
+    pub(crate) created_at: DateTime<Local>,
+    pub(crate) courses: Vec<Course>,
 }
 
 pub struct Course {
-    id: IdName,
-    created_at: DateTime<Local>,
-    modules: Vec<Module>,
-    folder: Directory,
+    pub(crate) id: IdName,
+    pub(crate) created_at: DateTime<Local>,
+    pub(crate) modules: Vec<Module>,
+    pub(crate) folder: Directory,
+    pub(crate) assignments: Directory,
+    pub(crate) pages: Directory,
+    pub(crate) discussions: Directory,
+    pub(crate) announcements: Directory,
 }
 
 pub struct Module {
-    id: IdName,
-    completed_at: DateTime<Local>,
-    files: Vec<RegularFile>,
+    pub(crate) id: IdName,
+    pub(crate) completed_at: DateTime<Local>,
+    pub(crate) files: Vec<RegularFile>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -52,29 +56,37 @@ pub struct FileBase {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FileTime {
-    created_at: DateTime<Local>,
-    updated_at: Option<DateTime<Local>>,
-    modified_at: Option<DateTime<Local>>,
+    pub(crate) created_at: DateTime<Local>,
+    pub(crate) updated_at: Option<DateTime<Local>>,
+    pub(crate) modified_at: Option<DateTime<Local>>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Directory {
     pub(crate) base: FileBase,
     pub(crate) files: Vec<File>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct RegularFile {
-    base: FileBase,
+    pub(crate) base: FileBase,
+    #[serde(default)]
+    pub(crate) content_type: Option<String>,
+    /// For files made from what canvas returns inline, e.x. an assignment's description,
+    /// which are written out instead of downloaded.
+    /// Markdown documents are kept as html until then, see `html::DocumentFormat`.
+    /// Only needed until then, so not kept in sync.json.
+    #[serde(skip)]
+    pub(crate) contents: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub enum File {
     Directory(Directory),
     RegularFile(RegularFile),
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FileTree {
     pub(crate) api: CoreApi,
     pub(crate) root: Directory,
@@ -82,7 +94,7 @@ pub struct FileTree {
 
 impl Display for CanvasBase {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} @ {}", self.api.domain, self.id.name)?;
+        write!(f, "{} @ {}", self.api.domain(), self.id.name)?;
         Ok(())
     }
 }
@@ -94,7 +106,7 @@ fn to_directories<T: Into<Directory>>(vec: Vec<T>) -> impl Iterator<Item = File>
 }
 
 impl FileTime {
-    fn created_at(created_at: DateTime<Local>) -> FileTime {
+    pub(crate) fn created_at(created_at: DateTime<Local>) -> FileTime {
         FileTime {
             created_at,
             updated_at: None,
@@ -116,7 +128,7 @@ impl Default for FileTime {
 }
 
 impl FileBase {
-    fn directory(id: IdName, time: DateTime<Local>) -> FileBase {
+    pub(crate) fn directory(id: IdName, time: DateTime<Local>) -> FileBase {
         FileBase {
             id,
             time: FileTime::created_at(time),
@@ -127,6 +139,19 @@ impl FileBase {
     pub(crate) fn into_file(self) -> RegularFile {
         RegularFile {
             base: self,
+            content_type: None,
+            contents: None,
+        }
+    }
+    
+    /// A file written with `contents` instead of downloaded.
+    /// The size is only a guess for markdown documents, which are converted when written.
+    pub(crate) fn into_generated_file(mut self, content_type: &str, contents: String) -> RegularFile {
+        self.size = Optioned::some(contents.len() as u64);
+        RegularFile {
+            base: self,
+            content_type: Some(content_type.to_owned()),
+            contents: Some(contents),
         }
     }
     
@@ -178,9 +203,17 @@ impl From<Course> for Directory {
             created_at,
             modules,
             folder,
+            assignments,
+            pages,
+            discussions,
+            announcements,
         } = course;
-        let mut files = Vec::with_capacity(1 + modules.len());
+        let mut files = Vec::with_capacity(5 + modules.len());
         files.push(File::Directory(folder));
+        files.push(File::Directory(assignments));
+        files.push(File::Directory(pages));
+        files.push(File::Directory(discussions));
+        files.push(File::Directory(announcements));
         files.extend(to_directories(modules));
         Self {
             base: FileBase::directory(id, created_at),
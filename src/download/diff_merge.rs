@@ -17,10 +17,11 @@ impl Directory {
             .collect()
     }
     
-    fn id_to_file_map_mut(&mut self) -> HashMap<Id, File> {
+    fn id_to_index_map(&self) -> HashMap<Id, usize> {
         self.files
-            .drain(..)
-            .map(|file| (file.id(), file))
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (file.id(), i))
             .collect()
     }
 }
@@ -58,21 +59,20 @@ impl<T: FileDiff> Diff for T {
 
 impl FileDiff for Directory {
     fn diff_id_unchecked(self, old: &Self) -> Option<Self> {
-        // TODO check if in canvas the directory time is updated when one of its files is updated
-        Some(self)
-            .filter(|new| new.is_newer_than(old))
-            .map(|new| {
-                let old_files_map = old.id_to_file_map();
-                let files = new
-                    .files
-                    .into_iter()
-                    .filter_map(|new_file| match old_files_map.get(&new_file.id()) {
-                        None => Some(new_file),
-                        Some(old_file) => new_file.diff(old_file),
-                    })
-                    .collect();
-                new.base.into_directory(files)
+        // canvas doesn't update a directory's time when one of its files is updated,
+        // so always recurse and keep the directory if anything in it changed
+        let is_newer = self.is_newer_than(old);
+        let old_files_map = old.id_to_file_map();
+        let Self { base, files } = self;
+        let files = files
+            .into_iter()
+            .filter_map(|new_file| match old_files_map.get(&new_file.id()) {
+                None => Some(new_file),
+                Some(old_file) => new_file.diff(old_file),
             })
+            .collect::<Vec<_>>();
+        Some(base.into_directory(files))
+            .filter(|new| is_newer || !new.files.is_empty())
     }
 }
 
@@ -119,28 +119,28 @@ impl Merge for FileTree {
 impl Merge for Directory {
     fn merge(&mut self, diff: Self) {
         self.base.time = diff.base.time;
-        let mut old_files_map = self.id_to_file_map_mut();
-        // can't push and merge at the same time b/c that'd use two mut borrows
-        // so I rebuild whole self.files at once
-        self.files = diff.files
-            .into_iter()
-            .map(|new_file| match old_files_map.remove(&new_file.id()) {
-                None => new_file,
-                Some(mut old_file) => {
-                    old_file.merge(new_file);
-                    old_file
-                },
-            })
-            .collect();
+        // files not in the diff are unchanged, so they have to be kept
+        let old_indices = self.id_to_index_map();
+        for new_file in diff.files {
+            match old_indices.get(&new_file.id()) {
+                None => self.files.push(new_file),
+                Some(&i) => self.files[i].merge(new_file),
+            }
+        }
     }
 }
 
 impl Merge for RegularFile {
     fn merge(&mut self, diff: Self) {
+        let RegularFile {
+            base: new,
+            content_type,
+            ..
+        } = diff;
         let old = self.base_mut();
-        let new = diff.into_base();
         old.time = new.time;
         old.size = new.size;
+        self.content_type = content_type;
     }
 }
 
@@ -1,12 +1,16 @@
-use crate::download::data::{GetFileBase, FileBase, FileTime};
+use crate::download::data::{GetFileBase, FileBase, FileTime, RegularFile};
 use std::path::{PathBuf, Path};
 use chrono::{DateTime, Local};
 use std::error::Error;
 use crate::api::core::CoreApi;
+use crate::download::html::DocumentFormat;
+use crate::download::markdown;
 
 pub struct Download {
     file: FileBase,
     pub(crate) path: PathBuf,
+    // written instead of downloading the file if there are any
+    contents: Option<String>,
 }
 
 pub(crate) trait GetFileBaseExt: GetFileBase {
@@ -21,10 +25,24 @@ pub(crate) trait GetFileBaseExt: GetFileBase {
         Download {
             file: self.into_base(),
             path,
+            contents: None,
         }
     }
 }
 
+impl RegularFile {
+    pub(crate) fn to_download(&self, path: &Path) -> Download {
+        let mut download = self.base().clone().into_download(path);
+        download.contents = self.contents
+            .as_ref()
+            .map(|contents| match self.content_type.as_deref() {
+                Some(DocumentFormat::MARKDOWN) => markdown::from_html(contents),
+                _ => contents.clone(),
+            });
+        download
+    }
+}
+
 impl<T: GetFileBase> GetFileBaseExt for T {}
 
 impl FileTime {
@@ -64,9 +82,18 @@ impl Download {
     }
     
     pub(crate) async fn download_as_file(&self, api: &CoreApi) -> Result<(), Box<dyn Error>> {
-        let mut file = async_std::fs::File::open(self.path()).await?;
-        let mut resp = api.download(&self.file.id()).await?;
-        async_std::io::copy(&mut resp, &mut file).await?;
+        if let Some(contents) = &self.contents {
+            async_std::fs::write(self.path(), contents).await?;
+            self.set_time()?;
+            return Ok(());
+        }
+        let what = self.path().display().to_string();
+        api.retry(&what, move || async move {
+            let mut file = async_std::fs::File::create(self.path()).await?;
+            let mut resp = api.download(&self.file.id()).await?;
+            async_std::io::copy(&mut resp, &mut file).await?;
+            Ok(())
+        }).await?;
         self.set_time()?;
         Ok(())
     }
@@ -5,7 +5,6 @@ use crate::download::download::{Download, GetFileBaseExt};
 use crate::download::diff_merge::{Diff, Merge};
 use std::error::Error;
 use crate::util::future::FutureIterator;
-use std::io::{Write, Read};
 
 // need to separate into immut and mut parts
 pub struct Downloads {
@@ -16,7 +15,6 @@ pub struct Downloads {
 pub struct DownloadsImmut {
     root: PathBuf,
     ignore: Gitignore,
-    file_tree_file: std::fs::File,
     current_file_tree: FileTree,
 }
 
@@ -25,38 +23,12 @@ pub struct DownloadsMut {
     files: Vec<Download>,
 }
 
-fn open_and_parse_file_tree(root: &Path) -> Result<(std::fs::File, FileTree), Box<dyn Error>> {
-    let path = {
-        let mut path = root.to_path_buf();
-        path.push("file_tree.json");
-        path
-    };
-    let mut file = std::fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .truncate(false)
-        .create(true)
-        .open(path)?;
-    let metadata = file.metadata()?; // shouldn't really be able to Err
-    let size = metadata.len() as usize;
-    let file_tree = if size == 0 {
-        todo!()
-    } else {
-        let mut bytes = Vec::with_capacity(size + 1);
-        file.read_to_end(&mut bytes)?;
-        serde_json::from_slice(bytes.as_ref())?
-    };
-    Ok((file, file_tree))
-}
-
 impl DownloadsImmut {
-    fn new(root: PathBuf) -> Result<Self, Box<dyn Error>> {
+    fn new(root: PathBuf, current_file_tree: FileTree) -> Result<Self, Box<dyn Error>> {
         let ignore = GitignoreBuilder::new(root.as_path()).build()?;
-        let (file_tree_file, current_file_tree) = open_and_parse_file_tree(root.as_ref())?;
         Ok(Self {
             root,
             ignore,
-            file_tree_file,
             current_file_tree,
         })
     }
@@ -70,12 +42,6 @@ impl DownloadsImmut {
             .matched(download.path.as_path(), is_dir)
             .is_ignore()
     }
-    
-    fn save_current_file_tree(&mut self) -> Result<(), Box<dyn Error>> {
-        let bytes = serde_json::to_vec_pretty(&self.current_file_tree)?;
-        self.file_tree_file.write_all(bytes.as_ref())?;
-        Ok(())
-    }
 }
 
 impl DownloadsMut {
@@ -100,13 +66,17 @@ impl DownloadsMut {
 }
 
 impl Downloads {
-    pub fn new(root: PathBuf) -> Result<Self, Box<dyn Error>> {
+    pub fn new(root: PathBuf, current_file_tree: FileTree) -> Result<Self, Box<dyn Error>> {
         Ok(Self {
-            immut: DownloadsImmut::new(root)?,
+            immut: DownloadsImmut::new(root, current_file_tree)?,
             r#mut: DownloadsMut::new(),
         })
     }
     
+    pub fn into_file_tree(self) -> FileTree {
+        self.immut.current_file_tree
+    }
+    
     fn add_download(
         self_immut: &DownloadsImmut, self_mut: &mut DownloadsMut,
         download: Download, is_dir: bool,
@@ -141,11 +111,7 @@ impl Downloads {
                     );
                 }
                 File::RegularFile(file) => {
-                    let download = file
-                        .base()
-                        .clone()
-                        .into_file()
-                        .into_download(path);
+                    let download = file.to_download(path);
                     Self::add_download(
                         self_immut, self_mut,
                         download, false,
@@ -165,7 +131,6 @@ impl Downloads {
             &diff.root, self.immut.root(),
         );
         self.immut.current_file_tree.merge(diff);
-        self.immut.save_current_file_tree()?;
         Ok(())
     }
     
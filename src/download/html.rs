@@ -0,0 +1,258 @@
+// canvas returns assignment descriptions, pages, etc. as html fragments,
+// which are saved as standalone pages
+
+use crate::download::data::Id;
+use chrono::{DateTime, Local};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use url::Url;
+
+/// How documents like pages and assignment descriptions are exported.
+/// Set in sync.json's settings.
+/// Documents already exported are only converted once they're updated in canvas.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    Html,
+    /// Converted from the html, which is kept until the document is written.
+    Markdown,
+}
+
+impl Default for DocumentFormat {
+    fn default() -> Self {
+        DocumentFormat::Html
+    }
+}
+
+impl DocumentFormat {
+    pub const HTML: &'static str = "text/html";
+    pub const MARKDOWN: &'static str = "text/markdown";
+    
+    pub fn extension(self) -> &'static str {
+        match self {
+            DocumentFormat::Html => "html",
+            DocumentFormat::Markdown => "md",
+        }
+    }
+    
+    pub fn content_type(self) -> &'static str {
+        match self {
+            DocumentFormat::Html => Self::HTML,
+            DocumentFormat::Markdown => Self::MARKDOWN,
+        }
+    }
+    
+    /// `name` with this format's extension.
+    pub fn file_name(self, name: &str) -> String {
+        format!("{}.{}", name, self.extension())
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// How times are shown in documents, e.x. when a reply was posted.
+pub(crate) fn format_time(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// A complete html document with `body`, an html fragment, under a `title` heading.
+pub(crate) fn page(title: &str, body: &str) -> String {
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n\
+        <html>\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <title>{title}</title>\n\
+        </head>\n\
+        <body>\n\
+        <h1>{title}</h1>\n\
+        {body}\n\
+        </body>\n\
+        </html>\n",
+        title = title,
+        body = body,
+    )
+}
+
+/// Where the value of the attribute whose name ends at `start` in `html` is,
+/// quoted or not, e.x. `href = "..."` or `href=...`.
+fn value_range(html: &str, start: usize) -> Option<Range<usize>> {
+    let rest = html[start..].trim_start();
+    let rest = rest.strip_prefix('=')?.trim_start();
+    let start = html.len() - rest.len();
+    match rest.chars().next()? {
+        quote @ '"' | quote @ '\'' => {
+            let start = start + 1;
+            let len = html[start..].find(quote)?;
+            Some(start..start + len)
+        }
+        _ => {
+            let len = rest
+                .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                .unwrap_or(rest.len());
+            Some(start..start + len)
+        }
+    }
+}
+
+/// Where the values of the href and src attributes in `html` are.
+fn url_ranges(html: &str) -> Vec<Range<usize>> {
+    let mut ranges = ["href", "src"]
+        .iter()
+        .flat_map(|attribute| html
+            .match_indices(attribute)
+            .map(move |(i, _)| (i, i + attribute.len())))
+        // not e.x. data-href
+        .filter(|&(i, _)| html[..i].ends_with(|c: char| c.is_ascii_whitespace()))
+        .filter_map(|(_, end)| value_range(html, end))
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|it| it.start);
+    ranges
+}
+
+/// The id of the canvas file `url` links to,
+/// i.e. if it's a `/files/:id` or `/courses/:course_id/files/:id` url on this canvas,
+/// including e.x. `/files/:id/download`.
+fn file_id(url: &str, base_url: &Url) -> Option<Id> {
+    let origin = base_url.origin().ascii_serialization();
+    let path = match url.strip_prefix(origin.as_str()) {
+        Some(it) => it,
+        None if url.starts_with('/') && !url.starts_with("//") => url,
+        None => return None,
+    };
+    let path = &path[..path.find(|c: char| c == '?' || c == '#').unwrap_or(path.len())];
+    let id = |segment: &str| -> Option<Id> {
+        if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        segment.parse().ok()
+    };
+    let mut segments = path.strip_prefix('/')?.split('/');
+    match segments.next()? {
+        "files" => {}
+        "courses" => {
+            id(segments.next()?)?;
+            if segments.next()? != "files" {
+                return None;
+            }
+        }
+        _ => return None,
+    }
+    id(segments.next()?)
+}
+
+/// The ids of the canvas files linked from `html`.
+pub(crate) fn linked_file_ids(html: &str, base_url: &Url) -> Vec<Id> {
+    url_ranges(html)
+        .into_iter()
+        .filter_map(|range| file_id(&html[range], base_url))
+        .unique()
+        .collect()
+}
+
+/// Replaces every link to a canvas file in `html` with `local_url(id)`,
+/// unless it's None, e.x. if the file wasn't synced.
+pub(crate) fn rewrite_file_links(html: &str, base_url: &Url, local_url: impl Fn(Id) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut end = 0;
+    for range in url_ranges(html) {
+        if range.start < end {
+            continue;
+        }
+        let url = match file_id(&html[range.clone()], base_url).and_then(&local_url) {
+            None => continue,
+            Some(it) => it,
+        };
+        rewritten.push_str(&html[end..range.start]);
+        rewritten.push_str(&escape(&url));
+        end = range.end;
+    }
+    rewritten.push_str(&html[end..]);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_id, rewrite_file_links, url_ranges};
+    use url::Url;
+    
+    fn base_url() -> Url {
+        Url::parse("https://canvas.example.edu").unwrap()
+    }
+    
+    fn urls(html: &str) -> Vec<&str> {
+        url_ranges(html)
+            .into_iter()
+            .map(|range| &html[range])
+            .collect()
+    }
+    
+    #[test]
+    fn url_ranges_quoted() {
+        let html = r#"<a href="/a" data-href="/b">a</a><img alt='x' src='/c'>"#;
+        assert_eq!(urls(html), vec!["/a", "/c"]);
+    }
+    
+    #[test]
+    fn url_ranges_unquoted_and_spaced() {
+        let html = "<a href=/a>a</a><a class=x\nhref = \"/b\" title=t>b</a><img src =/c alt=c>";
+        assert_eq!(urls(html), vec!["/a", "/b", "/c"]);
+    }
+    
+    #[test]
+    fn url_ranges_skips_other_attributes() {
+        assert!(urls(r#"<img srcset="/a 2x"><a hreflang="en">x</a> src is href"#).is_empty());
+    }
+    
+    #[test]
+    fn file_ids() {
+        let id = |url: &str| file_id(url, &base_url());
+        assert_eq!(id("/courses/101/files/401"), Some(401));
+        assert_eq!(id("/files/403/download?download_frd=1"), Some(403));
+        assert_eq!(id("https://canvas.example.edu/courses/101/files/406/download"), Some(406));
+        assert_eq!(id("/files/407#preview"), Some(407));
+    }
+    
+    #[test]
+    fn file_ids_only_of_canvas_files() {
+        let id = |url: &str| file_id(url, &base_url());
+        assert_eq!(id("https://elsewhere.example.com/files/9"), None);
+        assert_eq!(id("https://canvas.example.edu.evil.com/files/9"), None);
+        assert_eq!(id("//canvas.example.edu/files/9"), None);
+        assert_eq!(id("/courses/101/pages/files/9"), None);
+        assert_eq!(id("/users/1/files/9"), None);
+        assert_eq!(id("/courses/101/files?preview=9"), None);
+        assert_eq!(id("/files/9abc"), None);
+        assert_eq!(id("/files/+9"), None);
+        assert_eq!(id("files/9"), None);
+    }
+    
+    #[test]
+    fn rewrites_only_synced_files() {
+        let html = r#"<a href="/files/1/download">one</a> <a href=/files/2>two</a> <img src="/files/3">"#;
+        let local_url = |id| match id {
+            1 => Some("../Files/a & b.txt".to_owned()),
+            2 => Some("two.txt".to_owned()),
+            _ => None,
+        };
+        assert_eq!(
+            rewrite_file_links(html, &base_url(), local_url),
+            r#"<a href="../Files/a &amp; b.txt">one</a> <a href=two.txt>two</a> <img src="/files/3">"#,
+        );
+    }
+}
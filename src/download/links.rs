@@ -0,0 +1,201 @@
+// exported documents link to canvas files by their canvas urls,
+// which are rewritten to where the files are synced so they still work offline
+
+use crate::download::data::{Course, Directory, File, GetFileBase, Id, RegularFile};
+use crate::download::html::{self, DocumentFormat};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Where each canvas file is synced, relative to the course's directory.
+/// A file can be synced in more than one place, e.x. both in Files and in a module.
+pub(crate) type FilePaths = HashMap<Id, Vec<PathBuf>>;
+
+impl RegularFile {
+    /// A page, description, etc. that's still html, even if it'll be written as markdown.
+    fn is_document(&self) -> bool {
+        self.contents.is_some() && match self.content_type.as_deref() {
+            Some(DocumentFormat::HTML) | Some(DocumentFormat::MARKDOWN) => true,
+            _ => false,
+        }
+    }
+}
+
+fn visit<F: FnMut(&Path, &RegularFile)>(dir: &Directory, parent: &Path, f: &mut F) {
+    let path = parent.join(&dir.base.id.name);
+    for file in &dir.files {
+        match file {
+            File::Directory(it) => visit(it, &path, f),
+            File::RegularFile(it) => f(&path, it),
+        }
+    }
+}
+
+fn visit_mut<F: FnMut(&Path, &mut RegularFile)>(dir: &mut Directory, parent: &Path, f: &mut F) {
+    let path = parent.join(&dir.base.id.name);
+    for file in &mut dir.files {
+        match file {
+            File::Directory(it) => visit_mut(it, &path, f),
+            File::RegularFile(it) => f(&path, it),
+        }
+    }
+}
+
+impl Course {
+    fn directories(&self) -> Vec<&Directory> {
+        vec![
+            &self.folder,
+            &self.assignments,
+            &self.pages,
+            &self.discussions,
+            &self.announcements,
+        ]
+    }
+    
+    fn directories_mut(&mut self) -> Vec<&mut Directory> {
+        vec![
+            &mut self.folder,
+            &mut self.assignments,
+            &mut self.pages,
+            &mut self.discussions,
+            &mut self.announcements,
+        ]
+    }
+    
+    /// Calls `f` with every file in the course and the directory it's in, relative to the course's directory.
+    fn for_each_file<F: FnMut(&Path, &RegularFile)>(&self, mut f: F) {
+        for dir in self.directories() {
+            visit(dir, Path::new(""), &mut f);
+        }
+        for module in &self.modules {
+            let path = Path::new(&module.id.name);
+            for file in &module.files {
+                f(path, file);
+            }
+        }
+    }
+    
+    /// Modules only have canvas files, so they're skipped.
+    fn for_each_document_mut<F: FnMut(&Path, &mut RegularFile)>(&mut self, mut f: F) {
+        for dir in self.directories_mut() {
+            visit_mut(dir, Path::new(""), &mut |path, file| if file.is_document() {
+                f(path, file);
+            });
+        }
+    }
+}
+
+pub(crate) fn file_paths(course: &Course) -> FilePaths {
+    let mut paths = FilePaths::new();
+    course.for_each_file(|dir, file| {
+        if file.contents.is_none() {
+            paths
+                .entry(file.id())
+                .or_default()
+                .push(dir.join(&file.base.id.name));
+        }
+    });
+    paths
+}
+
+/// The canvas files linked from the course's documents that aren't synced anywhere in the course.
+pub(crate) fn unsynced_links(course: &Course, paths: &FilePaths, base_url: &Url) -> Vec<Id> {
+    let mut ids = Vec::new();
+    course.for_each_file(|_, file| {
+        match &file.contents {
+            Some(contents) if file.is_document() => ids.extend(html::linked_file_ids(contents, base_url)),
+            _ => {}
+        }
+    });
+    ids.into_iter()
+        .filter(|id| !paths.contains_key(id))
+        .unique()
+        .collect()
+}
+
+/// Linked files that aren't synced anywhere else go at the top of Files,
+/// since they're in folders the student can't see.
+pub(crate) fn add_unsynced(course: &mut Course, paths: &mut FilePaths, files: Vec<RegularFile>) {
+    let folder = &mut course.folder;
+    for file in files {
+        paths
+            .entry(file.id())
+            .or_default()
+            .push(Path::new(&folder.base.id.name).join(&file.base.id.name));
+        folder.files.push(File::RegularFile(file));
+    }
+}
+
+/// Rewrites the links in every document in the course to canvas files that are synced,
+/// preferring a copy in the same directory as the document.
+pub(crate) fn rewrite_links(course: &mut Course, paths: &FilePaths, base_url: &Url) {
+    course.for_each_document_mut(|dir, file| {
+        let local_url = |id: Id| {
+            let paths = paths.get(&id)?;
+            let path = paths
+                .iter()
+                .find(|it| it.parent() == Some(dir))
+                .or_else(|| paths.first())?;
+            Some(relative_url(dir, path))
+        };
+        let contents = file.contents
+            .as_deref()
+            .map(|it| html::rewrite_file_links(it, base_url, local_url));
+        file.contents = contents;
+    });
+}
+
+fn encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// A relative url to `path` from a document in `dir`.
+fn relative_url(dir: &Path, path: &Path) -> String {
+    let from = dir.components().collect::<Vec<_>>();
+    let to = path.components().collect::<Vec<_>>();
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let up = std::iter::repeat("..".to_owned()).take(from.len() - common);
+    let down = to[common..]
+        .iter()
+        .map(|it| encode(&it.as_os_str().to_string_lossy()));
+    up.chain(down).join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::relative_url;
+    use std::path::Path;
+    
+    fn url(dir: &str, path: &str) -> String {
+        relative_url(Path::new(dir), Path::new(path))
+    }
+    
+    #[test]
+    fn same_directory() {
+        assert_eq!(url("Assignments/Homework 1", "Assignments/Homework 1/notes.txt"), "notes.txt");
+    }
+    
+    #[test]
+    fn goes_up_to_the_common_directory() {
+        assert_eq!(url("Pages", "Files/syllabus.pdf"), "../Files/syllabus.pdf");
+        assert_eq!(url("Discussions/Introductions", "Files/Week 1/a.txt"), "../../Files/Week%201/a.txt");
+        assert_eq!(url("Assignments/Homework 1", "Assignments/Homework 2/a.txt"), "../Homework%202/a.txt");
+    }
+    
+    #[test]
+    fn percent_encodes_names() {
+        assert_eq!(url("Pages", "Pages/100% #1?.txt"), "100%25%20%231%3F.txt");
+        assert_eq!(url("Pages", "Pages/caf\u{e9}.txt"), "caf%C3%A9.txt");
+    }
+}
@@ -0,0 +1,451 @@
+// a small html to markdown converter for what canvas's rich content editor produces,
+// so exported pages are readable as plain text;
+// anything it doesn't know, e.x. tables, is reduced to its text
+
+struct Converter {
+    markdown: String,
+    // one for each open list, with the number of the next item if it's ordered
+    lists: Vec<Option<u32>>,
+    links: Vec<Option<String>>,
+    // nothing's been written since the last list item's marker,
+    // so e.x. a paragraph inside it starts on the marker's line
+    item_start: bool,
+    in_pre: bool,
+    // inside inline code, where nothing's escaped
+    in_code: bool,
+    // inside the head, a script, or a style
+    skipping: bool,
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            return std::char::from_u32(code);
+        }
+    };
+    Some(c)
+}
+
+/// Replaces the entities in `text`, leaving any it doesn't know as is.
+fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let entity = rest[1..]
+            .find(';')
+            .map(|end| &rest[1..end + 1])
+            .and_then(|entity| Some((entity, decode_entity(entity)?)));
+        match entity {
+            Some((entity, c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The value of `name` in a tag like `a href="..." title='...'`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    // skip the tag's name
+    let mut rest = tag.trim_start_matches(|c: char| !c.is_ascii_whitespace());
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .find(|c: char| c == '=' || c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let attribute = &rest[..end];
+        rest = rest[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            None => "",
+            Some(it) => {
+                let it = it.trim_start();
+                let (value, after) = match it.chars().next() {
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        let it = &it[1..];
+                        let end = it.find(quote).unwrap_or(it.len());
+                        (&it[..end], it.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => it.split_at(it.find(|c: char| c.is_ascii_whitespace()).unwrap_or(it.len())),
+                };
+                rest = after;
+                value
+            }
+        };
+        if attribute.eq_ignore_ascii_case(name) {
+            return Some(decode(value));
+        }
+    }
+}
+
+/// Where the tag at the start of `html`, after its `<`, ends,
+/// i.e. its first `>` that isn't in a quoted attribute.
+fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut after_equals = false;
+    for (i, c) in html.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '>' => return Some(i),
+            None if after_equals && (c == '"' || c == '\'') => quote = Some(c),
+            None => {}
+        }
+        if !c.is_ascii_whitespace() {
+            after_equals = c == '=';
+        }
+    }
+    None
+}
+
+/// Escapes what markdown would take as formatting in `word`,
+/// which is at the start of a line if `line_start`, where e.x. # starts a heading.
+fn escape(word: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(word.len());
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        let special = match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' => true,
+            // only where it could start an html tag
+            '<' => chars.peek().map_or(false, |&it| it.is_ascii_alphabetic() || "/!?".contains(it)),
+            '#' | '>' | '-' | '+' | '=' => line_start && escaped.is_empty(),
+            _ => false,
+        };
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    // e.x. 1. would start an ordered list
+    if line_start {
+        let digits = escaped.bytes().take_while(u8::is_ascii_digit).count();
+        if digits > 0 && digits + 1 == escaped.len() && escaped.ends_with(|c: char| c == '.' || c == ')') {
+            escaped.insert(digits, '\\');
+        }
+    }
+    escaped
+}
+
+/// A link's url, in angle brackets if it has anything that would end it early.
+fn destination(url: &str) -> String {
+    if url.contains(|c: char| c.is_ascii_whitespace() || "()<>".contains(c)) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_owned()
+    }
+}
+
+impl Converter {
+    fn new() -> Self {
+        Self {
+            markdown: String::new(),
+            lists: Vec::new(),
+            links: Vec::new(),
+            item_start: false,
+            in_pre: false,
+            in_code: false,
+            skipping: false,
+        }
+    }
+    
+    fn at_line_start(&self) -> bool {
+        self.markdown.is_empty() || self.markdown.ends_with('\n')
+    }
+    
+    fn trim_end_spaces(&mut self) {
+        let len = self.markdown.trim_end_matches(' ').len();
+        self.markdown.truncate(len);
+    }
+    
+    fn new_line(&mut self) {
+        if self.item_start {
+            return;
+        }
+        self.trim_end_spaces();
+        if !self.at_line_start() {
+            self.markdown.push('\n');
+        }
+    }
+    
+    fn blank_line(&mut self) {
+        if self.item_start {
+            return;
+        }
+        self.new_line();
+        if !self.markdown.is_empty() && !self.markdown.ends_with("\n\n") {
+            self.markdown.push('\n');
+        }
+    }
+    
+    /// What's written at the start of a line is indented to continue the list item it's in.
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if !self.in_pre && self.at_line_start() {
+            let indent = "    ".repeat(self.lists.len());
+            self.markdown.push_str(&indent);
+        }
+        self.markdown.push_str(s);
+        self.item_start = false;
+    }
+    
+    fn text(&mut self, text: &str) {
+        if self.skipping {
+            return;
+        }
+        let text = decode(text);
+        if self.in_pre {
+            self.markdown.push_str(&text);
+            return;
+        }
+        for (i, word) in text.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+            let space = i > 0 || text.starts_with(|c: char| c.is_ascii_whitespace());
+            if space && !self.at_line_start() && !self.markdown.ends_with(' ') {
+                self.markdown.push(' ');
+            }
+            if self.in_code {
+                self.write(word);
+            } else {
+                let line_start = self.at_line_start() || self.item_start;
+                self.write(&escape(word, line_start));
+            }
+        }
+    }
+    
+    fn list_item(&mut self) {
+        // an empty item is still its own line
+        self.item_start = false;
+        self.new_line();
+        let depth = self.lists.len().saturating_sub(1);
+        self.markdown.push_str(&"    ".repeat(depth));
+        match self.lists.last_mut() {
+            Some(Some(n)) => {
+                self.markdown.push_str(&format!("{}. ", n));
+                *n += 1;
+            }
+            _ => self.markdown.push_str("- "),
+        }
+        self.item_start = true;
+    }
+    
+    fn open(&mut self, name: &str, tag: &str) {
+        match name {
+            "head" | "script" | "style" => self.skipping = true,
+            "p" | "div" | "blockquote" | "table" => self.blank_line(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.blank_line();
+                let level = name[1..].parse().unwrap_or(1);
+                self.write(&"#".repeat(level));
+                self.write(" ");
+            }
+            "br" => {
+                self.trim_end_spaces();
+                self.markdown.push_str("  \n");
+            }
+            "hr" => {
+                self.blank_line();
+                self.write("---");
+                self.blank_line();
+            }
+            "strong" | "b" => self.write("**"),
+            "em" | "i" => self.write("_"),
+            "code" if !self.in_pre => {
+                self.write("`");
+                self.in_code = true;
+            }
+            "pre" => {
+                self.blank_line();
+                self.markdown.push_str("```\n");
+                self.in_pre = true;
+            }
+            "ul" => {
+                self.new_line();
+                self.lists.push(None);
+            }
+            "ol" => {
+                self.new_line();
+                self.lists.push(Some(1));
+            }
+            "li" => self.list_item(),
+            "tr" => self.new_line(),
+            "td" | "th" => {
+                if !self.at_line_start() {
+                    self.markdown.push_str(" | ");
+                }
+            }
+            "a" => {
+                let href = attribute(tag, "href");
+                if href.is_some() {
+                    self.write("[");
+                }
+                self.links.push(href);
+            }
+            "img" => {
+                if let Some(src) = attribute(tag, "src") {
+                    let alt = attribute(tag, "alt").unwrap_or_default();
+                    let alt = alt
+                        .split_ascii_whitespace()
+                        .map(|word| escape(word, false))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    self.write(&format!("![{}]({})", alt, destination(&src)));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    fn close(&mut self, name: &str) {
+        match name {
+            "head" | "script" | "style" => self.skipping = false,
+            "p" | "div" | "blockquote" | "table"
+            | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.blank_line(),
+            "strong" | "b" => self.write("**"),
+            "em" | "i" => self.write("_"),
+            "code" if !self.in_pre => {
+                self.in_code = false;
+                self.write("`");
+            }
+            "pre" => {
+                self.in_pre = false;
+                self.new_line();
+                self.markdown.push_str("```");
+                self.blank_line();
+            }
+            "ul" | "ol" => {
+                self.item_start = false;
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            "a" => {
+                if let Some(Some(href)) = self.links.pop() {
+                    self.markdown.push_str(&format!("]({})", destination(&href)));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    fn tag(&mut self, tag: &str) {
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(it) => (true, it),
+            None => (false, tag),
+        };
+        let tag = tag.trim_end_matches('/');
+        let name = tag
+            .split(|c: char| c.is_ascii_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if closing {
+            self.close(&name);
+        } else if !self.skipping {
+            self.open(&name, tag);
+        }
+    }
+}
+
+/// Converts an html document or fragment to markdown.
+pub(crate) fn from_html(html: &str) -> String {
+    let mut converter = Converter::new();
+    let mut rest = html;
+    while let Some(i) = rest.find('<') {
+        converter.text(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment
+                .find("-->")
+                .map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = tag_end(rest).unwrap_or(rest.len());
+        converter.tag(&rest[..end]);
+        rest = rest.get(end + 1..).unwrap_or_default();
+    }
+    converter.text(rest);
+    let markdown = converter.markdown.trim_matches('\n');
+    format!("{}\n", markdown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_html;
+    
+    #[test]
+    fn paragraphs_and_headings() {
+        let html = "<h2>Week 1</h2><p>Read  the\nsyllabus.</p><p>Then the <strong>rules</strong>, <em>carefully</em>.</p>";
+        assert_eq!(from_html(html), "## Week 1\n\nRead the syllabus.\n\nThen the **rules**, _carefully_.\n");
+    }
+    
+    #[test]
+    fn line_breaks_and_rules() {
+        assert_eq!(from_html("<p>one<br>two<br/>three</p><hr><p>four</p>"), "one  \ntwo  \nthree\n\n---\n\nfour\n");
+    }
+    
+    #[test]
+    fn ordered_and_nested_lists() {
+        let html = "<ol><li>first<ul><li>a</li><li>b</li></ul></li><li><p>second</p><p>more</p></li></ol>";
+        assert_eq!(from_html(html), "1. first\n    - a\n    - b\n2. second\n\n    more\n");
+    }
+    
+    #[test]
+    fn code() {
+        assert_eq!(from_html("<p>run <code>a_b *c*</code></p>"), "run `a_b *c*`\n");
+        let html = "<pre><code>fn main() {\n    *x = 1;\n}</code></pre>";
+        assert_eq!(from_html(html), "```\nfn main() {\n    *x = 1;\n}\n```\n");
+    }
+    
+    #[test]
+    fn links_and_images() {
+        let html = r#"<a href="/files/1?a=1&amp;b=2">the rules</a> <img alt="a *cat*" src="cat (1).png"> <a name="top">x</a>"#;
+        assert_eq!(from_html(html), "[the rules](/files/1?a=1&b=2) ![a \\*cat\\*](<cat (1).png>) x\n");
+    }
+    
+    #[test]
+    fn quoted_greater_than() {
+        let html = r#"<a title="1 > 0" href='x>y'>link</a>"#;
+        assert_eq!(from_html(html), "[link](<x%3Ey>)\n");
+    }
+    
+    #[test]
+    fn entities_and_comments() {
+        let html = "<p>&lt;b&gt; &amp; &#233;&#x41; &bogus;<!-- <p>hidden</p> --></p>";
+        assert_eq!(from_html(html), "\\<b> & éA &bogus;\n");
+    }
+    
+    #[test]
+    fn escapes_markdown() {
+        let html = "<p># not a heading, a_b * [x] 1.</p><p>2. not a list</p><p>- nor this</p>";
+        assert_eq!(from_html(html), "\\# not a heading, a\\_b \\* \\[x\\] 1.\n\n2\\. not a list\n\n\\- nor this\n");
+    }
+    
+    #[test]
+    fn skips_what_isnt_content() {
+        let html = "<html><head><title>t</title><style>p {}</style></head><body><script>x()</script><table><tr><td>a</td><td>b</td></tr></table></body></html>";
+        assert_eq!(from_html(html), "a | b\n");
+    }
+}
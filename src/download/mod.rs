@@ -2,3 +2,6 @@ pub mod data;
 pub mod downloads;
 mod download;
 mod diff_merge;
+pub mod html;
+mod markdown;
+pub(crate) mod links;
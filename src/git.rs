@@ -0,0 +1,92 @@
+use crate::state::lock::SYNC_LOCK;
+use crate::state::migrate::BACKUP_GLOB;
+use std::error::Error;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Runs git as a subprocess in the sync dir, since git2 is large and complex.
+pub struct Git<'a> {
+    dir: &'a Path,
+}
+
+impl<'a> Git<'a> {
+    pub fn new(dir: &'a Path) -> Self {
+        Self { dir }
+    }
+    
+    fn run(&self, args: &[&str]) -> Result<Output, Box<dyn Error>> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(self.dir)
+            .output()
+            .map_err(|e| format!("couldn't run git (use --skip-git to not use git): {}", e))?;
+        if !output.status.success() {
+            let error_message = std::str::from_utf8(&*output.stderr)?;
+            return Err(format!("git {} failed: {}", args.join(" "), error_message.trim()).into());
+        }
+        Ok(output)
+    }
+    
+    // `git status` would also succeed if dir were nested in another repo
+    fn is_repo(&self) -> bool {
+        self.dir.join(".git").exists()
+    }
+    
+    fn is_clean(&self) -> Result<bool, Box<dyn Error>> {
+        // we already hold the lock, so it exists even if it isn't ignored yet
+        let exclude_lock = format!(":(exclude){}", SYNC_LOCK);
+        let output = self.run(&["status", "--porcelain", "--", ".", exclude_lock.as_str()])?;
+        Ok(output.stdout.is_empty())
+    }
+    
+    /// Adds whatever of our own files aren't already in the .gitignore.
+    fn ignore(&self) -> std::io::Result<()> {
+        let path = self.dir.join(".gitignore");
+        let mut gitignore = match std::fs::read_to_string(&path) {
+            Ok(it) => it,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let ignored = [SYNC_LOCK, BACKUP_GLOB];
+        let missing = ignored
+            .iter()
+            .map(|it| format!("/{}", it))
+            .filter(|it| !gitignore.lines().any(|line| line == it))
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        if !gitignore.is_empty() && !gitignore.ends_with('\n') {
+            gitignore.push('\n');
+        }
+        for line in missing {
+            gitignore.push_str(&line);
+            gitignore.push('\n');
+        }
+        std::fs::write(path, gitignore)
+    }
+    
+    /// Run before changing anything, so every commit only contains our own changes.
+    pub fn prepare(&self) -> Result<(), Box<dyn Error>> {
+        if !self.is_repo() {
+            self.run(&["init", "--quiet"])?;
+        } else if !self.is_clean()? {
+            return Err(format!(
+                "{} has uncommitted changes, commit them first or use --skip-git",
+                self.dir.display(),
+            ).into());
+        }
+        self.ignore()?;
+        Ok(())
+    }
+    
+    /// Does nothing if nothing changed.
+    pub fn commit_all(&self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.run(&["add", "--all"])?;
+        if self.is_clean()? {
+            return Ok(());
+        }
+        self.run(&["commit", "--quiet", "--message", message])?;
+        Ok(())
+    }
+}
@@ -2,33 +2,181 @@
 #![allow(dead_code)]
 
 use std::path::PathBuf;
-use crate::download::data::CanvasBase;
+use crate::download::data::{CanvasBase, IdName};
 use std::error::Error;
+use async_std::task;
+use crate::api::CoreApi;
+use crate::state::{SyncState, SyncLock, Credentials};
+use std::time::Duration;
+use crate::git::Git;
+use crate::api::transport::{self, Cassette, RecordingTransport, ReplayTransport, Transport, REDACTED};
+use std::sync::Arc;
 
 pub mod api;
 pub mod download;
+pub mod state;
+mod git;
 mod util;
 
 pub struct CanvasFileSync {
     pub dir: PathBuf,
     pub skip_git: bool,
+    /// How long to wait for another sync to release sync.json, or forever if None.
+    pub lock_timeout: Option<Duration>,
+    /// Where access tokens are stored, outside of `dir`.
+    pub credentials: PathBuf,
+    /// Record every canvas request and response of a sync into this cassette, with access tokens redacted.
+    pub record: Option<PathBuf>,
+    /// Serve a sync entirely from this cassette instead of canvas.
+    pub replay: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub struct AddUser {
     pub access_token: String,
     pub search: String,
+    /// Only consider the canvas at this domain.
+    pub domain: Option<String>,
+    /// Skip the search and use the canvas at this url,
+    /// for canvases that aren't on canvas.instructure.com, like self-hosted ones.
+    pub base_url: Option<String>,
 }
 
 impl CanvasFileSync {
+    fn lock(&self) -> Result<SyncLock, Box<dyn Error>> {
+        SyncLock::acquire(&self.dir, self.lock_timeout)
+    }
+    
+    fn git(&self) -> Option<Git> {
+        Some(Git::new(&self.dir))
+            .filter(|_| !self.skip_git)
+    }
+    
+    fn prepare_git(&self) -> Result<(), Box<dyn Error>> {
+        self.git()
+            .map(|git| git.prepare())
+            .unwrap_or(Ok(()))
+    }
+    
+    fn commit(&self, message: &str) -> Result<(), Box<dyn Error>> {
+        self.git()
+            .map(|git| git.commit_all(message))
+            .unwrap_or(Ok(()))
+    }
+    
+    /// Searches every institution on canvas.instructure.com,
+    /// keeping only the one at `add_user.domain` if given.
+    pub fn search(add_user: &AddUser) -> Result<Vec<CanvasBase>, Box<dyn Error>> {
+        let AddUser {
+            access_token,
+            search,
+            domain,
+            base_url,
+        } = add_user;
+        if let Some(base_url) = base_url {
+            let api = CoreApi::new(CoreApi::parse_base_url(base_url)?, access_token.clone());
+            let name = match search.as_str() {
+                "" => api.domain(),
+                search => search.to_owned(),
+            };
+            let canvas = CanvasBase {
+                api,
+                id: IdName {
+                    id: 0,
+                    name,
+                },
+            };
+            return Ok(vec![canvas]);
+        }
+        // account search also matches domains
+        let search = match domain {
+            Some(domain) if search.is_empty() => domain,
+            _ => search,
+        };
+        let accounts = task::block_on(CoreApi::account_search().search_accounts(search))?;
+        let canvases = accounts
+            .into_iter()
+            .filter(|it| domain
+                .as_ref()
+                .map_or(true, |domain| it.domain == *domain))
+            .map(|it| it.into_canvas(access_token.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(canvases)
+    }
+    
+    /// `select_canvas` chooses which of the canvases found to add, even if there's only one,
+    /// e.x. so an explicit choice that's out of range still fails.
     pub fn add_user<F>(&self, add_user: AddUser, select_canvas: F) -> Result<(), Box<dyn Error>>
         where F: FnOnce(Vec<CanvasBase>) -> Result<CanvasBase, Box<dyn Error>> {
-        dbg!(add_user);
-        select_canvas(vec![])?;
-        todo!()
+        let canvases = Self::search(&add_user)?;
+        if canvases.is_empty() {
+            return Err(match &add_user.domain {
+                None => format!("no canvas found for \"{}\"", add_user.search),
+                Some(domain) => format!("no canvas found at {} for \"{}\"", domain, add_user.search),
+            }.into());
+        }
+        let canvas = select_canvas(canvases)?;
+        let user = task::block_on(canvas.api.current_user())
+            .map_err(|e| format!("access token doesn't work for {}: {}", canvas, e))?;
+        let _lock = self.lock()?;
+        self.prepare_git()?;
+        let mut credentials = Credentials::load(&self.credentials)?;
+        let mut state = SyncState::load(&self.dir, &mut credentials)?;
+        credentials.insert(&canvas.api.domain(), user.id, add_user.access_token);
+        credentials.save()?;
+        let message = format!("{} in {}", user.name, canvas.id.name);
+        if !state.add_user(canvas, user) {
+            println!("Updated access token for {}.", message);
+            return Ok(());
+        }
+        state.save(&self.dir)?;
+        let message = format!("Added {}.", message);
+        self.commit(&message)?;
+        println!("{}", message);
+        Ok(())
     }
     
+    /// A replay only writes the files it syncs, leaving sync.json and git as they were,
+    /// so the same cassette can be replayed again.
     pub fn sync(&self) -> Result<(), Box<dyn Error>> {
-        todo!()
+        let _lock = self.lock()?;
+        if self.replay.is_none() {
+            self.prepare_git()?;
+        }
+        let mut credentials = Credentials::load(&self.credentials)?;
+        let mut state = SyncState::load(&self.dir, &mut credentials)?;
+        if let Some(cassette) = &self.replay {
+            let exchanges = Cassette::load(cassette)?.into_exchanges()?;
+            let transport: Arc<dyn Transport> = Arc::new(ReplayTransport::new(exchanges));
+            for api in state.apis_mut() {
+                api.transport = transport.clone();
+                // whoever recorded the cassette doesn't have to share their token
+                if api.access_token().is_empty() {
+                    api.set_access_token(REDACTED);
+                }
+            }
+        }
+        let recorder = self.record
+            .as_ref()
+            .map(|_| Arc::new(RecordingTransport::new(transport::default())));
+        if let Some(recorder) = &recorder {
+            for api in state.apis_mut() {
+                api.transport = recorder.clone();
+            }
+        }
+        let result = task::block_on(state.sync(&self.dir));
+        if self.replay.is_some() {
+            return result;
+        }
+        // failed syncs are the ones worth recording, so it's saved even if sync.json can't be
+        let recorded = match (&self.record, &recorder) {
+            (Some(cassette), Some(recorder)) => Cassette::new(recorder.exchanges(), &state.access_tokens()).save(cassette),
+            _ => Ok(()),
+        };
+        // save and commit whichever users did sync even if others failed
+        state.save(&self.dir)?;
+        recorded?;
+        self.commit("Synced new files.")?;
+        result
     }
 }
@@ -0,0 +1,82 @@
+use crate::download::data::Id;
+use crate::util;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const CREDENTIALS_JSON: &str = "credentials.json";
+
+/// Access tokens, kept outside the synced (and committed) dir.
+/// Stored as domain -> user id -> access token.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Credentials {
+    #[serde(skip)]
+    path: PathBuf,
+    tokens: BTreeMap<String, BTreeMap<Id, String>>,
+}
+
+impl Credentials {
+    pub fn default_path() -> Option<PathBuf> {
+        let mut path = dirs::config_dir()?;
+        path.push(env!("CARGO_PKG_NAME"));
+        path.push(CREDENTIALS_JSON);
+        Some(path)
+    }
+    
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut this = match std::fs::File::open(path) {
+            Ok(mut file) => {
+                let bytes = util::fs::read_all(&mut file)?;
+                serde_json::from_slice(bytes.as_ref())?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        this.path = path.to_owned();
+        Ok(this)
+    }
+    
+    #[cfg(unix)]
+    fn create(path: &Path) -> std::io::Result<std::fs::File> {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // mode is only used if the file is new
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    
+    #[cfg(not(unix))]
+    fn create(path: &Path) -> std::io::Result<std::fs::File> {
+        std::fs::File::create(path)
+    }
+    
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let bytes = serde_json::to_vec_pretty(self)?;
+        Self::create(&self.path)?.write_all(bytes.as_ref())?;
+        Ok(())
+    }
+    
+    pub fn get(&self, domain: &str, user: Id) -> Option<&str> {
+        self.tokens
+            .get(domain)?
+            .get(&user)
+            .map(|it| it.as_str())
+    }
+    
+    pub fn insert(&mut self, domain: &str, user: Id, access_token: String) {
+        self.tokens
+            .entry(domain.to_owned())
+            .or_default()
+            .insert(user, access_token);
+    }
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const SYNC_LOCK: &str = "sync.json.lock";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An advisory lock on sync.json, held from loading it until after saving it.
+/// The OS releases it if we crash, so it can't go stale.
+pub struct SyncLock {
+    file: File,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if result == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    match e.kind() {
+        io::ErrorKind::WouldBlock => Ok(false),
+        _ => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn unlock(file: &File) {
+    use std::os::unix::io::AsRawFd;
+    unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) };
+}
+
+// TODO use LockFileEx on windows
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn unlock(_file: &File) {}
+
+impl SyncLock {
+    pub fn path(dir: &Path) -> PathBuf {
+        let mut path = dir.to_path_buf();
+        path.push(SYNC_LOCK);
+        path
+    }
+    
+    /// Waits for the lock, forever if `timeout` is None.
+    pub fn acquire(dir: &Path, timeout: Option<Duration>) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(&path)?;
+        let this = Self { file, path };
+        if try_lock(&this.file)? {
+            return Ok(this);
+        }
+        eprintln!("{} is locked by another sync, waiting...", this.path.display());
+        let start = Instant::now();
+        loop {
+            if let Some(timeout) = timeout {
+                if start.elapsed() >= timeout {
+                    return Err(format!(
+                        "timed out after {}s waiting for the lock on {}",
+                        timeout.as_secs(), this.path.display(),
+                    ).into());
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+            if try_lock(&this.file)? {
+                return Ok(this);
+            }
+        }
+    }
+}
+
+impl Drop for SyncLock {
+    fn drop(&mut self) {
+        unlock(&self.file);
+    }
+}
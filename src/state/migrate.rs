@@ -0,0 +1,190 @@
+use crate::api::core::{CoreApi, AUTHORIZATION_PREFIX};
+use crate::state::credentials::Credentials;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::path::Path;
+
+/// Bump this and add a migration to `MIGRATIONS` whenever the sync.json format changes.
+pub const CURRENT_VERSION: u64 = 4;
+
+/// Backups are named sync.json.v{version}.bak.
+/// They shouldn't be committed, since old versions can contain access tokens.
+pub const BACKUP_GLOB: &str = "sync.json.v*.bak";
+
+const VERSION: &str = "version";
+
+type Migration = fn(&mut Map<String, Value>, &mut Credentials) -> Result<(), Box<dyn Error>>;
+
+// MIGRATIONS[i] upgrades version i to version i + 1
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [
+    v0_to_v1,
+    v1_to_v2,
+    v2_to_v3,
+    v3_to_v4,
+];
+
+// v0 had no version or settings
+fn v0_to_v1(state: &mut Map<String, Value>, _: &mut Credentials) -> Result<(), Box<dyn Error>> {
+    state.insert("settings".into(), Value::Object(Map::new()));
+    Ok(())
+}
+
+fn array_mut<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+// v1 stored each user's authorization in their file tree's api
+fn v1_to_v2(state: &mut Map<String, Value>, credentials: &mut Credentials) -> Result<(), Box<dyn Error>> {
+    let canvases = state
+        .get_mut("canvases")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+    let mut moved = false;
+    for canvas in canvases {
+        let domain = canvas["domain"]
+            .as_str()
+            .ok_or("canvas without a domain")?
+            .to_owned();
+        for user in array_mut(canvas, "users") {
+            let id = user["id"]["id"]
+                .as_u64()
+                .ok_or("user without an id")?;
+            let api = user
+                .pointer_mut("/file_tree/api")
+                .and_then(Value::as_object_mut)
+                .ok_or("user without a file tree")?;
+            if let Some(Value::String(authorization)) = api.remove("authorization") {
+                let access_token = authorization.trim_start_matches(AUTHORIZATION_PREFIX);
+                credentials.insert(&domain, id, access_token.to_owned());
+                moved = true;
+            }
+        }
+    }
+    if moved {
+        eprintln!(
+            "Moved access tokens out of sync.json. \
+            If sync.json was already committed, they're still in the git history."
+        );
+    }
+    Ok(())
+}
+
+// v2 only stored the api's domain, which always meant https
+fn v2_to_v3(state: &mut Map<String, Value>, _: &mut Credentials) -> Result<(), Box<dyn Error>> {
+    let canvases = state
+        .get_mut("canvases")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+    for canvas in canvases {
+        for user in array_mut(canvas, "users") {
+            let api = user
+                .pointer_mut("/file_tree/api")
+                .and_then(Value::as_object_mut)
+                .ok_or("user without a file tree")?;
+            let domain = match api.remove("domain") {
+                Some(Value::String(domain)) => domain,
+                _ => return Err("file tree without a domain".into()),
+            };
+            let base_url = CoreApi::parse_base_url(&domain)?;
+            api.insert("base_url".into(), base_url.as_str().into());
+        }
+    }
+    Ok(())
+}
+
+// v3 gave the directories that aren't in canvas small ids, which could collide with canvas's,
+// so Files keeps its state under its new id, and the rest are exported again
+fn v3_to_v4(state: &mut Map<String, Value>, _: &mut Credentials) -> Result<(), Box<dyn Error>> {
+    // fetch::FILES_ID as of v4
+    const FILES_ID: u64 = 1 << 63;
+    const EXPORTS: [(u64, &str); 4] = [
+        (1, "Assignments"),
+        (2, "Pages"),
+        (3, "Discussions"),
+        (4, "Announcements"),
+    ];
+    let canvases = state
+        .get_mut("canvases")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+    for canvas in canvases {
+        for user in array_mut(canvas, "users") {
+            let root = user
+                .pointer_mut("/file_tree/root")
+                .ok_or("user without a file tree")?;
+            let courses = array_mut(root, "files")
+                .filter_map(|it| it.get_mut("Directory"))
+                .flat_map(|it| array_mut(it, "files"))
+                .filter_map(|it| it.get_mut("Directory"));
+            for course in courses {
+                let files = match course.get_mut("files").and_then(Value::as_array_mut) {
+                    None => continue,
+                    Some(it) => it,
+                };
+                files.retain(|file| {
+                    let id = &file["Directory"]["base"]["id"];
+                    !EXPORTS
+                        .iter()
+                        .any(|&(old, name)| id["id"] == old && id["name"] == name)
+                });
+                let files_id = files
+                    .iter_mut()
+                    .filter_map(|it| it.pointer_mut("/Directory/base/id"))
+                    .find(|it| it["id"] == 0 && it["name"] == "Files");
+                if let Some(id) = files_id {
+                    id["id"] = FILES_ID.into();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn version_of(state: &Map<String, Value>) -> Result<u64, Box<dyn Error>> {
+    match state.get(VERSION) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("invalid sync.json version: {}", version).into()),
+    }
+}
+
+fn backup(path: &Path, version: u64) -> std::io::Result<()> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", version));
+    std::fs::copy(path, backup)?;
+    Ok(())
+}
+
+/// Upgrades the sync.json at `path` to `CURRENT_VERSION`,
+/// first backing up the original if it's older.
+/// Returns if anything was migrated.
+pub fn migrate(path: &Path, state: &mut Value, credentials: &mut Credentials)
+    -> Result<bool, Box<dyn Error>> {
+    let state = state
+        .as_object_mut()
+        .ok_or("sync.json is not an object")?;
+    let version = version_of(state)?;
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "sync.json is version {}, but this version of {} only understands up to version {}",
+            version, env!("CARGO_PKG_NAME"), CURRENT_VERSION,
+        ).into());
+    }
+    if version == CURRENT_VERSION {
+        return Ok(false);
+    }
+    backup(path, version)?;
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(state, credentials)?;
+    }
+    state.insert(VERSION.into(), CURRENT_VERSION.into());
+    Ok(true)
+}
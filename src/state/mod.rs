@@ -0,0 +1,9 @@
+pub mod sync_state;
+pub mod migrate;
+pub mod lock;
+pub mod credentials;
+
+pub use self::sync_state::SyncState;
+pub use self::migrate::CURRENT_VERSION;
+pub use self::lock::SyncLock;
+pub use self::credentials::Credentials;
@@ -0,0 +1,245 @@
+use crate::api::core::CoreApi;
+use crate::api::retry::RetryPolicy;
+use crate::api::user::SelfUser;
+use crate::download::data::{Canvas, CanvasBase, FileTree, IdName, User, GetFileBase};
+use crate::download::downloads::Downloads;
+use crate::download::html::DocumentFormat;
+use crate::state::credentials::Credentials;
+use crate::state::migrate::{self, CURRENT_VERSION};
+use crate::util;
+use crate::util::future::FutureIterator;
+use chrono::{DateTime, Local};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const SYNC_JSON: &str = "sync.json";
+
+/// The whole sync.json document.
+/// Older versions are migrated by `migrate::migrate` on load.
+#[derive(Serialize, Deserialize)]
+pub struct SyncState {
+    pub version: u64,
+    pub settings: Settings,
+    pub canvases: Vec<CanvasState>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// html or markdown
+    #[serde(default)]
+    pub documents: DocumentFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CanvasState {
+    pub id: IdName,
+    pub domain: String,
+    pub users: Vec<UserState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserState {
+    pub id: IdName,
+    // starts from the sync dir, not dir/canvas/user
+    pub file_tree: FileTree,
+}
+
+impl Default for SyncState {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            settings: Settings::default(),
+            canvases: Vec::new(),
+        }
+    }
+}
+
+impl CanvasState {
+    fn new(canvas: &CanvasBase) -> Self {
+        Self {
+            id: canvas.id.clone(),
+            domain: canvas.api.domain(),
+            users: Vec::new(),
+        }
+    }
+    
+    fn has_user(&self, user: &SelfUser) -> bool {
+        self.users
+            .iter()
+            .any(|it| it.id.id == user.id)
+    }
+}
+
+impl UserState {
+    fn new(canvas: CanvasBase, user: SelfUser) -> Self {
+        let id = user.id_name();
+        let user = User {
+            id: id.clone(),
+            created_at: user.created_at.unwrap_or_else(Local::now),
+            courses: Vec::new(),
+        };
+        let canvas = Canvas {
+            base: canvas,
+            users: vec![user],
+        };
+        Self {
+            id,
+            file_tree: canvas.into(),
+        }
+    }
+    
+    fn created_at(&self) -> DateTime<Local> {
+        self.file_tree.root.files
+            .iter()
+            .find(|it| it.id() == self.id.id)
+            .map(|it| it.base().time.created_at)
+            .unwrap_or_else(Local::now)
+    }
+    
+    /// If anything fails, the old file tree is kept so the next sync tries again.
+    async fn sync(&mut self, dir: &Path, canvas: &IdName) -> Result<(), Box<dyn Error>> {
+        let api = &self.file_tree.api;
+        if api.access_token().is_empty() {
+            return Err("no access token, add this user again".into());
+        }
+        let user = api.fetch_user(self.id.clone(), self.created_at()).await?;
+        let canvas = Canvas {
+            base: CanvasBase {
+                api: api.clone(),
+                id: canvas.clone(),
+            },
+            users: vec![user],
+        };
+        let mut downloads = Downloads::new(dir.to_owned(), self.file_tree.clone())?;
+        downloads.add_file_tree(canvas.into())?;
+        downloads.download().await?;
+        self.file_tree = downloads.into_file_tree();
+        Ok(())
+    }
+}
+
+impl SyncState {
+    pub fn path(dir: &Path) -> PathBuf {
+        let mut path = dir.to_path_buf();
+        path.push(SYNC_JSON);
+        path
+    }
+    
+    /// Access tokens are filled in from `credentials`,
+    /// which older versions of sync.json may be migrated into.
+    pub fn load(dir: &Path, credentials: &mut Credentials) -> Result<Self, Box<dyn Error>> {
+        let path = Self::path(dir);
+        let mut file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let bytes = util::fs::read_all(&mut file)?;
+        let mut value = serde_json::from_slice(bytes.as_ref())?;
+        if migrate::migrate(&path, &mut value, credentials)? {
+            credentials.save()?;
+        }
+        let mut this: Self = serde_json::from_value(value)?;
+        this.authorize(credentials);
+        this.apply_settings();
+        Ok(this)
+    }
+    
+    fn authorize(&mut self, credentials: &Credentials) {
+        for canvas in &mut self.canvases {
+            for user in &mut canvas.users {
+                if let Some(access_token) = credentials.get(&canvas.domain, user.id.id) {
+                    user.file_tree.api.set_access_token(access_token);
+                }
+            }
+        }
+    }
+    
+    fn apply_settings(&mut self) {
+        let retry_policy = self.settings.retry.clone();
+        let document_format = self.settings.documents;
+        for api in self.apis_mut() {
+            api.retry_policy = retry_policy.clone();
+            api.document_format = document_format;
+        }
+    }
+    
+    /// Every user's api.
+    pub(crate) fn apis_mut(&mut self) -> impl Iterator<Item = &mut CoreApi> {
+        self.canvases
+            .iter_mut()
+            .flat_map(|it| it.users.iter_mut())
+            .map(|it| &mut it.file_tree.api)
+    }
+    
+    pub fn access_tokens(&self) -> Vec<String> {
+        self.canvases
+            .iter()
+            .flat_map(|it| it.users.iter())
+            .map(|it| it.file_tree.api.access_token().to_owned())
+            .filter(|it| !it.is_empty())
+            .collect()
+    }
+    
+    pub fn save(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        self.version = CURRENT_VERSION;
+        let bytes = serde_json::to_vec_pretty(self)?;
+        std::fs::write(Self::path(dir), bytes)?;
+        Ok(())
+    }
+    
+    fn canvas_mut(&mut self, canvas: &CanvasBase) -> &mut CanvasState {
+        let domain = canvas.api.domain();
+        let i = match self.canvases
+            .iter()
+            .position(|it| it.domain == domain) {
+            Some(i) => i,
+            None => {
+                self.canvases.push(CanvasState::new(canvas));
+                self.canvases.len() - 1
+            }
+        };
+        &mut self.canvases[i]
+    }
+    
+    /// Syncs every user in every canvas concurrently.
+    /// Users that fail don't stop the others from syncing.
+    pub async fn sync(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let errors = self.canvases
+            .iter_mut()
+            .flat_map(|CanvasState { id, users, .. }| {
+                let canvas: &IdName = id;
+                users
+                    .iter_mut()
+                    .map(move |user| async move {
+                        user.sync(dir, canvas)
+                            .await
+                            .map_err(|e| format!("failed to sync {} in {}: {}", user.id.name, canvas.name, e))
+                    })
+            })
+            .join_all()
+            .await
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(errors.iter().join("\n").into());
+        }
+        Ok(())
+    }
+    
+    /// Returns false if the user was already added to this canvas.
+    pub fn add_user(&mut self, canvas: CanvasBase, user: SelfUser) -> bool {
+        let canvas_state = self.canvas_mut(&canvas);
+        if canvas_state.has_user(&user) {
+            return false;
+        }
+        canvas_state.users.push(UserState::new(canvas, user));
+        true
+    }
+}
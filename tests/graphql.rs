@@ -0,0 +1,38 @@
+mod mock;
+
+use crate::mock::{Fixture, MockCanvas};
+use async_std::task;
+use canvas_file_sync::api::CoreApi;
+use chrono::Local;
+
+#[test]
+fn graphql_modules_follow_end_cursor() {
+    let canvas = MockCanvas::start(Fixture::load("basic"));
+    let api = CoreApi::new(canvas.base_url(), canvas.access_token().into());
+    let modules = task::block_on(api.graphql_modules("101")).unwrap();
+    let names = modules
+        .into_iter()
+        .map(|it| it.name.unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Week 1", "Week 2"]);
+    // per_page is 1, so each module is its own page
+    let queries = canvas
+        .requests()
+        .into_iter()
+        .filter(|it| it == "POST /api/graphql")
+        .count();
+    assert_eq!(queries, 2);
+}
+
+#[test]
+fn graphql_modules_resolve_file_items() {
+    let canvas = MockCanvas::start(Fixture::load("basic"));
+    let api = CoreApi::new(canvas.base_url(), canvas.access_token().into());
+    let modules = task::block_on(api.graphql_modules("101")).unwrap();
+    let files = modules[0].files(Local::now());
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].id, 401);
+    assert_eq!(files[0].display_name, "syllabus.pdf");
+    assert_eq!(files[0].content_type.as_deref(), Some("application/pdf"));
+    assert!(modules[1].files(Local::now()).is_empty());
+}
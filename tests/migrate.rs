@@ -0,0 +1,220 @@
+mod mock;
+
+use crate::mock::TempDir;
+use canvas_file_sync::state::migrate::{self, CURRENT_VERSION};
+use canvas_file_sync::state::{Credentials, SyncState};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+const DOMAIN: &str = "canvas.example.edu";
+// fetch::FILES_ID as of v4
+const FILES_ID: u64 = 1 << 63;
+
+fn directory(id: u64, name: &str, files: Vec<Value>) -> Value {
+    json!({
+        "Directory": {
+            "base": {
+                "id": { "id": id, "name": name },
+                "time": {
+                    "created_at": "2020-01-10T12:00:00Z",
+                    "updated_at": null,
+                    "modified_at": null,
+                },
+                "size": null,
+            },
+            "files": files,
+        }
+    })
+}
+
+fn regular_file(id: u64, name: &str) -> Value {
+    json!({
+        "RegularFile": {
+            "base": {
+                "id": { "id": id, "name": name },
+                "time": {
+                    "created_at": "2020-01-10T12:00:00Z",
+                    "updated_at": "2020-01-11T12:00:00Z",
+                    "modified_at": null,
+                },
+                "size": 7,
+            },
+        }
+    })
+}
+
+/// A sync.json with one user in one course, whose directories are `course`,
+/// in the format of `version`, or v0's if None.
+fn sync_json(version: Option<u64>, api: Value, course: Vec<Value>) -> Value {
+    let root = directory(0, "Mock University", vec![
+        directory(1, "Test Student", vec![
+            directory(101, "Intro to Testing", course),
+        ]),
+    ]);
+    let mut state = json!({
+        "canvases": [{
+            "id": { "id": 0, "name": "Mock University" },
+            "domain": DOMAIN,
+            "users": [{
+                "id": { "id": 1, "name": "Test Student" },
+                "file_tree": {
+                    "api": api,
+                    "root": root["Directory"],
+                },
+            }],
+        }],
+    });
+    if let Some(version) = version {
+        state["version"] = version.into();
+        state["settings"] = json!({});
+    }
+    state
+}
+
+/// What canvas's files were in before v4.
+fn old_files() -> Vec<Value> {
+    vec![
+        directory(0, "Files", vec![regular_file(401, "syllabus.pdf")]),
+        directory(601, "Week 1", vec![regular_file(401, "syllabus.pdf")]),
+    ]
+}
+
+struct Setup {
+    _home: TempDir,
+    sync: TempDir,
+    credentials: Credentials,
+}
+
+impl Setup {
+    /// Writes `state` as sync.json.
+    fn new(state: &Value) -> Self {
+        let home = TempDir::new("home");
+        let sync = TempDir::new("sync");
+        let credentials = Credentials::load(&home.path().join("credentials.json")).unwrap();
+        std::fs::write(SyncState::path(sync.path()), serde_json::to_vec_pretty(state).unwrap()).unwrap();
+        Self {
+            _home: home,
+            sync,
+            credentials,
+        }
+    }
+    
+    fn backup(&self, version: u64) -> PathBuf {
+        self.sync.path().join(format!("sync.json.v{}.bak", version))
+    }
+    
+    /// Migrates sync.json as it's loaded, returning the migrated json and if it was migrated.
+    fn migrate(&mut self) -> (Value, bool) {
+        let path = SyncState::path(self.sync.path());
+        let mut state = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let migrated = migrate::migrate(&path, &mut state, &mut self.credentials).unwrap();
+        (state, migrated)
+    }
+}
+
+fn course_files(state: &Value) -> &Vec<Value> {
+    state
+        .pointer("/canvases/0/users/0/file_tree/root/files/0/Directory/files/0/Directory/files")
+        .and_then(Value::as_array)
+        .unwrap()
+}
+
+fn directory_ids(files: &[Value]) -> Vec<(u64, &str)> {
+    files
+        .iter()
+        .map(|it| &it["Directory"]["base"]["id"])
+        .map(|id| (id["id"].as_u64().unwrap(), id["name"].as_str().unwrap()))
+        .collect()
+}
+
+/// The shape of every version, once migrated.
+fn assert_current(state: &Value) {
+    assert_eq!(state["version"], CURRENT_VERSION);
+    assert!(state["settings"].is_object());
+    let api = &state["canvases"][0]["users"][0]["file_tree"]["api"];
+    assert_eq!(api, &json!({ "base_url": "https://canvas.example.edu/" }));
+    let files = course_files(state);
+    assert_eq!(directory_ids(files), vec![(FILES_ID, "Files"), (601, "Week 1")]);
+    // what's in the directories is kept
+    assert_eq!(files[0]["Directory"]["files"][0]["RegularFile"]["base"]["id"]["id"], 401);
+    serde_json::from_value::<SyncState>(state.clone()).unwrap();
+}
+
+#[test]
+fn migrates_v0() {
+    let v0 = sync_json(None, json!({
+        "domain": DOMAIN,
+        "authorization": "Bearer secret",
+    }), old_files());
+    let mut setup = Setup::new(&v0);
+    let (state, migrated) = setup.migrate();
+    assert!(migrated);
+    assert_current(&state);
+    assert_eq!(setup.credentials.get(DOMAIN, 1), Some("secret"));
+    let backup: Value = serde_json::from_slice(&std::fs::read(setup.backup(0)).unwrap()).unwrap();
+    assert_eq!(backup, v0);
+}
+
+#[test]
+fn migrates_v1() {
+    let mut setup = Setup::new(&sync_json(Some(1), json!({
+        "domain": DOMAIN,
+        "authorization": "Bearer secret",
+    }), old_files()));
+    let (state, migrated) = setup.migrate();
+    assert!(migrated);
+    assert_current(&state);
+    assert_eq!(setup.credentials.get(DOMAIN, 1), Some("secret"));
+    assert!(setup.backup(1).is_file());
+}
+
+#[test]
+fn migrates_v2() {
+    let mut setup = Setup::new(&sync_json(Some(2), json!({
+        "domain": DOMAIN,
+    }), old_files()));
+    let (state, migrated) = setup.migrate();
+    assert!(migrated);
+    assert_current(&state);
+    assert_eq!(setup.credentials.get(DOMAIN, 1), None);
+    assert!(setup.backup(2).is_file());
+}
+
+#[test]
+fn migrates_v3() {
+    let mut course = old_files();
+    course.extend(vec![
+        directory(1, "Assignments", vec![directory(301, "Homework 1", Vec::new())]),
+        directory(2, "Pages", Vec::new()),
+        directory(3, "Discussions", Vec::new()),
+        directory(4, "Announcements", Vec::new()),
+    ]);
+    let mut setup = Setup::new(&sync_json(Some(3), json!({
+        "base_url": "https://canvas.example.edu/",
+    }), course));
+    let (state, migrated) = setup.migrate();
+    assert!(migrated);
+    // the exports are dropped, so they're exported again under their new ids
+    assert_current(&state);
+    assert!(setup.backup(3).is_file());
+}
+
+#[test]
+fn keeps_the_current_version() {
+    let mut setup = Setup::new(&sync_json(Some(CURRENT_VERSION), json!({
+        "base_url": "https://canvas.example.edu/",
+    }), Vec::new()));
+    let (_, migrated) = setup.migrate();
+    assert!(!migrated);
+    assert!(!setup.backup(CURRENT_VERSION).exists());
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let mut setup = Setup::new(&sync_json(Some(CURRENT_VERSION + 1), json!({
+        "base_url": "https://canvas.example.edu/",
+    }), Vec::new()));
+    let e = SyncState::load(setup.sync.path(), &mut setup.credentials).err().unwrap();
+    assert!(e.to_string().contains(&format!("sync.json is version {}", CURRENT_VERSION + 1)));
+    assert!(!setup.backup(CURRENT_VERSION + 1).exists());
+}
@@ -0,0 +1,433 @@
+// a stand-in for a canvas instance, serving fixtures from tests/fixtures
+
+#![allow(dead_code)]
+
+use async_std::io::BufReader;
+use async_std::net::{SocketAddr, TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+fn default_per_page() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+pub struct Fixture {
+    pub access_token: String,
+    /// Max page size of every list, so small fixtures still exercise pagination.
+    #[serde(default = "default_per_page")]
+    pub per_page: usize,
+    /// REST endpoint (relative to /api/v1/) to response.
+    /// Arrays are paginated with Link headers like canvas does.
+    #[serde(default)]
+    pub rest: HashMap<String, Value>,
+    /// GraphQL operation name to response data.
+    /// If the query has a `course_id`, the data is further keyed by it.
+    /// Connections (objects with `nodes` and `pageInfo`) are paged by the `after` variable.
+    #[serde(default)]
+    pub graphql: HashMap<String, Value>,
+    /// File id to contents, served at /files/:id/download.
+    #[serde(default)]
+    pub files: HashMap<String, String>,
+}
+
+impl Fixture {
+    pub fn load(name: &str) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(format!("{}.json", name));
+        let bytes = std::fs::read(&path)
+            .unwrap_or_else(|e| panic!("couldn't read {}: {}", path.display(), e));
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|e| panic!("invalid fixture {}: {}", path.display(), e))
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: serde_json::to_vec(body).unwrap(),
+        }
+    }
+    
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "errors": [{ "message": message }] }))
+    }
+    
+    // what canvas sends once a token's bucket is empty
+    fn rate_limited() -> Self {
+        Self {
+            status: 403,
+            content_type: "text/plain",
+            headers: vec![("X-Rate-Limit-Remaining", "0.0".to_owned())],
+            body: b"403 Forbidden (Rate Limit Exceeded)".to_vec(),
+        }
+    }
+    
+    fn into_bytes(self) -> Vec<u8> {
+        let mut head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status, self.content_type, self.body.len(),
+        );
+        for (name, value) in self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut bytes = head.into_bytes();
+        bytes.extend(self.body);
+        bytes
+    }
+}
+
+struct Request {
+    method: String,
+    url: Url,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn query(&self, key: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+/// Decrements `counter` if it isn't 0 yet.
+fn take_one(counter: &AtomicUsize) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+}
+
+struct Server {
+    addr: SocketAddr,
+    fixture: Fixture,
+    /// The fixture's, which tests can change between syncs.
+    rest: Mutex<HashMap<String, Value>>,
+    /// Endpoints that always fail, with the status they fail with.
+    failing: Mutex<HashMap<String, u16>>,
+    requests: Mutex<Vec<String>>,
+    rate_limited: AtomicUsize,
+    unavailable: AtomicUsize,
+}
+
+impl Server {
+    fn respond(&self, request: &Request) -> Response {
+        let path = request.url.path();
+        self.requests
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", request.method, path));
+        let authorization = format!("Bearer {}", self.fixture.access_token);
+        if request.headers.get("authorization") != Some(&authorization) {
+            return Response::error(401, "Invalid access token.");
+        }
+        if take_one(&self.rate_limited) {
+            return Response::rate_limited();
+        }
+        if take_one(&self.unavailable) {
+            return Response::error(503, "Service Unavailable");
+        }
+        let mut response = self.route(request);
+        response.headers.push(("X-Rate-Limit-Remaining", "700.0".to_owned()));
+        response.headers.push(("X-Request-Cost", "1.0".to_owned()));
+        response
+    }
+    
+    fn route(&self, request: &Request) -> Response {
+        let path = request.url.path();
+        if path == "/api/graphql" && request.method == "POST" {
+            return self.graphql(request);
+        }
+        const REST: &str = "/api/v1/";
+        if path.starts_with(REST) {
+            return self.rest(request, &path[REST.len()..]);
+        }
+        const FILES: &str = "/files/";
+        const DOWNLOAD: &str = "/download";
+        if path.starts_with(FILES) && path.ends_with(DOWNLOAD) {
+            return self.download(&path[FILES.len()..path.len() - DOWNLOAD.len()]);
+        }
+        Response::error(404, "The specified resource does not exist.")
+    }
+    
+    fn rest(&self, request: &Request, endpoint: &str) -> Response {
+        if let Some(&status) = self.failing.lock().unwrap().get(endpoint) {
+            return Response::error(status, "Internal Server Error");
+        }
+        let rest = self.rest.lock().unwrap();
+        let value = match rest.get(endpoint) {
+            None => return Response::error(404, "The specified resource does not exist."),
+            Some(it) => it,
+        };
+        let items = match value.as_array() {
+            None => return Response::json(200, value),
+            Some(it) => it,
+        };
+        let per_page = request
+            .query("per_page")
+            .and_then(|it| it.parse().ok())
+            .unwrap_or(self.fixture.per_page)
+            .min(self.fixture.per_page)
+            .max(1);
+        let page = request
+            .query("page")
+            .and_then(|it| it.parse().ok())
+            .unwrap_or(1usize)
+            .max(1);
+        let last = ((items.len() + per_page - 1) / per_page).max(1);
+        let page_items = items
+            .iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .cloned()
+            .collect::<Vec<_>>();
+        let link = |page: usize, rel: &str| {
+            let mut url = request.url.clone();
+            let pairs = request.url
+                .query_pairs()
+                .filter(|(k, _)| k != "page" && k != "per_page")
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect::<Vec<_>>();
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(pairs)
+                .append_pair("page", &page.to_string())
+                .append_pair("per_page", &per_page.to_string());
+            format!("<{}>; rel=\"{}\"", url, rel)
+        };
+        let mut links = vec![link(page, "current")];
+        if page < last {
+            links.push(link(page + 1, "next"));
+        }
+        if page > 1 {
+            links.push(link(page - 1, "prev"));
+        }
+        links.push(link(1, "first"));
+        links.push(link(last, "last"));
+        let mut response = Response::json(200, &Value::Array(page_items));
+        response.headers.push(("Link", links.join(",")));
+        response
+    }
+    
+    fn graphql(&self, request: &Request) -> Response {
+        let body: Value = match serde_json::from_slice(&request.body) {
+            Err(_) => return Response::error(400, "invalid json"),
+            Ok(it) => it,
+        };
+        let operation = body["operationName"].as_str().unwrap_or_default();
+        let mut data = match self.fixture.graphql.get(operation) {
+            None => return Response::json(200, &json!({ "data": null })),
+            Some(it) => it,
+        };
+        if let Some(course_id) = body["variables"]["course_id"].as_str() {
+            data = &data[course_id];
+        }
+        let mut data = data.clone();
+        let after = body["variables"]["after"]
+            .as_str()
+            .and_then(|it| it.parse().ok());
+        self.page_connections(&mut data, after);
+        Response::json(200, &json!({ "data": data }))
+    }
+    
+    /// Cursors are just the index of the last node on the page.
+    fn page_connections(&self, value: &mut Value, after: Option<usize>) {
+        let object = match value.as_object_mut() {
+            None => return,
+            Some(it) => it,
+        };
+        if object.contains_key("pageInfo") {
+            if let Some(nodes) = object.get_mut("nodes").and_then(Value::as_array_mut) {
+                let start = after.map(|it| it + 1).unwrap_or(0).min(nodes.len());
+                let end = (start + self.fixture.per_page).min(nodes.len());
+                let has_next_page = end < nodes.len();
+                *nodes = nodes[start..end].to_vec();
+                let end_cursor = if end > start { json!((end - 1).to_string()) } else { Value::Null };
+                object.insert("pageInfo".into(), json!({
+                    "hasNextPage": has_next_page,
+                    "endCursor": end_cursor,
+                }));
+                return;
+            }
+        }
+        for child in object.values_mut() {
+            self.page_connections(child, after);
+        }
+    }
+    
+    fn download(&self, id: &str) -> Response {
+        match self.fixture.files.get(id) {
+            None => Response::error(404, "The specified resource does not exist."),
+            Some(contents) => Response {
+                status: 200,
+                content_type: "application/octet-stream",
+                headers: Vec::new(),
+                body: contents.clone().into_bytes(),
+            },
+        }
+    }
+}
+
+async fn read_request(stream: &TcpStream, addr: SocketAddr) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let target = parts.next().unwrap_or_default();
+    let url = Url::parse(&format!("http://{}{}", addr, target))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(i) = line.find(':') {
+            headers.insert(line[..i].to_lowercase(), line[i + 1..].trim().to_owned());
+        }
+    }
+    if headers.get("expect").map(String::as_str) == Some("100-continue") {
+        let mut stream = stream;
+        stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+    }
+    let length = headers
+        .get("content-length")
+        .and_then(|it| it.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Request {
+        method,
+        url,
+        headers,
+        body,
+    })
+}
+
+async fn handle(stream: TcpStream, server: Arc<Server>) -> io::Result<()> {
+    let request = read_request(&stream, server.addr).await?;
+    let response = server.respond(&request);
+    let mut stream = &stream;
+    stream.write_all(&response.into_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+pub struct MockCanvas {
+    server: Arc<Server>,
+}
+
+impl MockCanvas {
+    pub fn start(mut fixture: Fixture) -> Self {
+        let rest = std::mem::take(&mut fixture.rest);
+        let listener = task::block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server {
+            addr,
+            fixture,
+            rest: Mutex::new(rest),
+            failing: Mutex::new(HashMap::new()),
+            requests: Mutex::new(Vec::new()),
+            rate_limited: AtomicUsize::new(0),
+            unavailable: AtomicUsize::new(0),
+        });
+        let accepting = server.clone();
+        task::spawn(async move {
+            let mut incoming = listener.incoming();
+            while let Some(stream) = incoming.next().await {
+                if let Ok(stream) = stream {
+                    task::spawn(handle(stream, accepting.clone()));
+                }
+            }
+        });
+        Self { server }
+    }
+    
+    pub fn base_url(&self) -> Url {
+        Url::parse(&format!("http://{}/", self.server.addr)).unwrap()
+    }
+    
+    pub fn access_token(&self) -> &str {
+        &self.server.fixture.access_token
+    }
+    
+    /// Answers the next `n` requests with 403 Forbidden (Rate Limit Exceeded).
+    pub fn rate_limit_next(&self, n: usize) {
+        self.server.rate_limited.store(n, Ordering::SeqCst);
+    }
+    
+    /// Answers the next `n` requests with 503 Service Unavailable.
+    pub fn fail_next(&self, n: usize) {
+        self.server.unavailable.store(n, Ordering::SeqCst);
+    }
+    
+    /// Changes the response of `endpoint` (relative to /api/v1/), like someone editing it in canvas.
+    pub fn edit(&self, endpoint: &str, edit: impl FnOnce(&mut Value)) {
+        let mut rest = self.server.rest.lock().unwrap();
+        edit(rest.get_mut(endpoint).unwrap());
+    }
+    
+    /// Answers every request to `endpoint` (relative to /api/v1/) with `status`.
+    pub fn fail_endpoint(&self, endpoint: &str, status: u16) {
+        self.server.failing.lock().unwrap().insert(endpoint.to_owned(), status);
+    }
+    
+    /// Every request so far, as "METHOD /path".
+    pub fn requests(&self) -> Vec<String> {
+        self.server.requests.lock().unwrap().clone()
+    }
+}
+
+static NEXT_TEMP_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "canvas-file-sync-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT_TEMP_DIR.fetch_add(1, Ordering::SeqCst),
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,147 @@
+use async_std::task;
+use futures::{Stream, StreamExt};
+use canvas_file_sync::api::transport::{BufferedResponse, FakeTransport, Method};
+use canvas_file_sync::api::CoreApi;
+use http_types::StatusCode;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::sync::Arc;
+
+const ITEMS: &str = "https://canvas.test/api/v1/items";
+
+// page last, like the urls get_list makes for the pages it fans out to
+fn page_url(page: u64) -> String {
+    format!("{}?per_page=2&page={}", ITEMS, page)
+}
+
+fn setup() -> (Arc<FakeTransport>, CoreApi) {
+    let fake = Arc::new(FakeTransport::new());
+    let base_url = CoreApi::parse_base_url("canvas.test").unwrap();
+    let api = CoreApi::new(base_url, "token".into()).with_transport(fake.clone());
+    (fake, api)
+}
+
+/// Answers `url` with items with `ids`, linking the pages in `links` by rel.
+fn respond_page(fake: &FakeTransport, url: &str, ids: &[u64], links: &[(&str, u64)]) {
+    let items = ids
+        .iter()
+        .map(|id| json!({ "id": id }))
+        .collect::<Vec<_>>();
+    let links = links
+        .iter()
+        .map(|(rel, page)| format!("<{}>; rel=\"{}\"", page_url(*page), rel))
+        .collect::<Vec<_>>()
+        .join(",");
+    fake.respond(Method::Get, url, BufferedResponse {
+        status: StatusCode::Ok,
+        headers: vec![
+            ("Content-Type".into(), "application/json".into()),
+            ("Link".into(), links),
+        ],
+        body: serde_json::to_vec(&items).unwrap(),
+    });
+}
+
+fn ids(items: Vec<Value>) -> Vec<u64> {
+    items
+        .into_iter()
+        .map(|it| it["id"].as_u64().unwrap())
+        .collect()
+}
+
+fn next_id(items: &mut (impl Stream<Item = Result<Value, Box<dyn Error>>> + Unpin)) -> Option<u64> {
+    task::block_on(items.next()).map(|it| it.unwrap()["id"].as_u64().unwrap())
+}
+
+#[test]
+fn get_list_fans_out_to_the_last_page() {
+    let (fake, api) = setup();
+    respond_page(&fake, ITEMS, &[1, 2], &[("next", 2), ("last", 3)]);
+    respond_page(&fake, &page_url(2), &[3, 4], &[("next", 3), ("last", 3)]);
+    respond_page(&fake, &page_url(3), &[5], &[("last", 3)]);
+    let items = task::block_on(api.get_list::<_, Value>("items", &json!({}))).unwrap();
+    assert_eq!(ids(items), vec![1, 2, 3, 4, 5]);
+    assert_eq!(fake.requests().len(), 3);
+}
+
+#[test]
+fn get_list_removes_items_repeated_by_shifted_pages() {
+    let (fake, api) = setup();
+    // an item was added while paginating, so page 2 starts with page 1's last item
+    respond_page(&fake, ITEMS, &[1, 2], &[("next", 2), ("last", 2)]);
+    respond_page(&fake, &page_url(2), &[2, 3], &[("last", 2)]);
+    let items = task::block_on(api.get_list::<_, Value>("items", &json!({}))).unwrap();
+    assert_eq!(ids(items), vec![1, 2, 3]);
+}
+
+#[test]
+fn get_list_follows_next_links_without_a_last_link() {
+    let (fake, api) = setup();
+    respond_page(&fake, ITEMS, &[1, 2], &[("next", 2)]);
+    respond_page(&fake, &page_url(2), &[3, 4], &[("next", 3)]);
+    respond_page(&fake, &page_url(3), &[5], &[]);
+    let items = task::block_on(api.get_list::<_, Value>("items", &json!({}))).unwrap();
+    assert_eq!(ids(items), vec![1, 2, 3, 4, 5]);
+    let urls = fake
+        .requests()
+        .into_iter()
+        .map(|it| it.url)
+        .collect::<Vec<_>>();
+    assert_eq!(urls, vec![ITEMS.to_owned(), page_url(2), page_url(3)]);
+}
+
+#[test]
+fn get_list_stops_at_too_many_pages() {
+    let (fake, api) = setup();
+    // canvas keeps linking the same next page
+    respond_page(&fake, ITEMS, &[1], &[("next", 2)]);
+    respond_page(&fake, &page_url(2), &[2], &[("next", 2)]);
+    let e = task::block_on(api.get_list::<_, Value>("items", &json!({}))).unwrap_err();
+    assert_eq!(e.to_string(), "items has more than 1000 pages");
+    assert_eq!(fake.requests().len(), 1000);
+}
+
+#[test]
+fn get_list_rejects_too_many_pages_before_requesting_them() {
+    let (fake, api) = setup();
+    respond_page(&fake, ITEMS, &[1], &[("next", 2), ("last", 1001)]);
+    let e = task::block_on(api.get_list::<_, Value>("items", &json!({}))).unwrap_err();
+    assert_eq!(e.to_string(), "items has more than 1000 pages");
+    assert_eq!(fake.requests().len(), 1);
+}
+
+#[test]
+fn stream_list_yields_items_page_by_page() {
+    let (fake, api) = setup();
+    respond_page(&fake, ITEMS, &[1, 2], &[("next", 2), ("last", 3)]);
+    respond_page(&fake, &page_url(2), &[2, 3], &[("next", 3), ("last", 3)]);
+    respond_page(&fake, &page_url(3), &[4], &[("last", 3)]);
+    let query = json!({});
+    let mut items = Box::pin(api.stream_list::<_, Value>("items", &query));
+    assert_eq!(next_id(&mut items), Some(1));
+    assert_eq!(fake.requests().len(), 1);
+    assert_eq!(next_id(&mut items), Some(2));
+    assert_eq!(fake.requests().len(), 1);
+    // the repeated 2 is skipped
+    assert_eq!(next_id(&mut items), Some(3));
+    assert_eq!(fake.requests().len(), 2);
+    assert_eq!(next_id(&mut items), Some(4));
+    assert_eq!(next_id(&mut items), None);
+    assert_eq!(fake.requests().len(), 3);
+}
+
+#[test]
+fn stream_list_stops_requesting_pages_once_dropped() {
+    let (fake, api) = setup();
+    respond_page(&fake, ITEMS, &[1, 2], &[("next", 2), ("last", 3)]);
+    respond_page(&fake, &page_url(2), &[3, 4], &[("next", 3), ("last", 3)]);
+    respond_page(&fake, &page_url(3), &[5], &[("last", 3)]);
+    let query = json!({});
+    let first = task::block_on(api.stream_list::<_, Value>("items", &query).take(3).collect::<Vec<_>>());
+    let first = first
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(ids(first), vec![1, 2, 3]);
+    assert_eq!(fake.requests().len(), 2);
+}
@@ -0,0 +1,643 @@
+mod mock;
+
+use crate::mock::{Fixture, MockCanvas, TempDir};
+use canvas_file_sync::api::retry::RetryPolicy;
+use canvas_file_sync::api::user::SelfUser;
+use canvas_file_sync::api::CoreApi;
+use canvas_file_sync::download::data::{CanvasBase, IdName};
+use canvas_file_sync::state::{Credentials, SyncState};
+use canvas_file_sync::{AddUser, CanvasFileSync};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CANVAS: &str = "Mock University";
+const USER: &str = "Test Student";
+
+struct Setup {
+    canvas: MockCanvas,
+    // the credentials have to be outside the synced dir
+    _home: TempDir,
+    sync: TempDir,
+    app: CanvasFileSync,
+}
+
+impl Setup {
+    /// Registers the fixture's user like `add` would, without searching canvas.instructure.com.
+    fn new(fixture: &str) -> Self {
+        let canvas = MockCanvas::start(Fixture::load(fixture));
+        let home = TempDir::new("home");
+        let sync = TempDir::new("sync");
+        let credentials = home.path().join("credentials.json");
+        let user = SelfUser {
+            id: 1,
+            name: USER.into(),
+            created_at: None,
+        };
+        let api = CoreApi::new(canvas.base_url(), canvas.access_token().into());
+        let mut saved_credentials = Credentials::load(&credentials).unwrap();
+        saved_credentials.insert(&api.domain(), user.id, canvas.access_token().into());
+        saved_credentials.save().unwrap();
+        let mut state = SyncState::default();
+        state.add_user(
+            CanvasBase {
+                api,
+                id: IdName {
+                    id: 0,
+                    name: CANVAS.into(),
+                },
+            },
+            user,
+        );
+        state.save(sync.path()).unwrap();
+        let app = CanvasFileSync {
+            dir: sync.path().to_owned(),
+            skip_git: true,
+            lock_timeout: Some(Duration::from_secs(5)),
+            credentials,
+            record: None,
+            replay: None,
+        };
+        Self {
+            canvas,
+            _home: home,
+            sync,
+            app,
+        }
+    }
+    
+    fn user_dir(&self) -> PathBuf {
+        self.sync.path().join(CANVAS).join(USER)
+    }
+    
+    fn sync_json(&self) -> String {
+        std::fs::read_to_string(SyncState::path(self.sync.path())).unwrap()
+    }
+    
+    /// The contents of a synced file, which has to be there.
+    fn read(&self, path: impl AsRef<Path>) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+    
+    /// Changes a setting in sync.json, like someone editing it by hand.
+    fn set_setting(&self, name: &str, value: Value) {
+        let path = SyncState::path(self.sync.path());
+        let mut state: Value = serde_json::from_str(&self.sync_json()).unwrap();
+        state["settings"][name] = value;
+        std::fs::write(path, serde_json::to_vec_pretty(&state).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn sync_creates_course_and_module_directories() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    assert!(course.join("Week 1").is_dir());
+    assert!(course.join("Week 2").is_dir());
+}
+
+#[test]
+fn sync_keeps_modules_apart_from_each_other_and_the_exports() {
+    let setup = Setup::new("basic");
+    setup.canvas.edit("courses/101/modules", |modules| {
+        let modules = modules.as_array_mut().unwrap();
+        for (id, name) in vec![(203, "Assignments"), (204, "Week 1")] {
+            modules.push(json!({
+                "id": id,
+                "name": name,
+                "position": 3,
+                "items_count": 0,
+                "completed_at": null,
+                "items": [],
+            }));
+        }
+    });
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    assert!(course.join("Assignments").join("Homework 1").is_dir());
+    assert!(course.join("Assignments (203)").is_dir());
+    assert!(course.join("Week 1").join("syllabus.pdf").is_file());
+    assert!(course.join("Week 1 (204)").is_dir());
+}
+
+#[test]
+fn sync_downloads_files_linked_from_modules() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    let week1 = std::fs::read_dir(course.join("Week 1"))
+        .unwrap()
+        .map(|it| it.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(week1, vec!["syllabus.pdf"]);
+    assert_eq!(setup.read(course.join("Week 1").join("syllabus.pdf")), "syllabus contents");
+    assert_eq!(std::fs::read_dir(course.join("Week 2")).unwrap().count(), 0);
+    assert!(setup.sync_json().contains("application/pdf"));
+}
+
+#[test]
+fn sync_downloads_course_files() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let files = setup.user_dir().join("Intro to Testing").join("Files");
+    assert_eq!(setup.read(files.join("syllabus.pdf")), "syllabus contents");
+    assert_eq!(setup.read(files.join("Lectures").join("lecture 1.pdf")), "lecture 1 slides");
+    // courses without a visible Files tab still sync
+    assert!(setup.user_dir().join("Advanced Mocking").join("Files").is_dir());
+}
+
+#[test]
+fn sync_keeps_folders_under_a_hidden_root_folder() {
+    let setup = Setup::new("basic");
+    setup.canvas.edit("courses/101/folders", |folders| {
+        folders[0]["hidden_for_user"] = json!(true);
+    });
+    setup.app.sync().unwrap();
+    let files = setup.user_dir().join("Intro to Testing").join("Files");
+    assert!(files.join("Lectures").join("lecture 1.pdf").is_file());
+    let requests = setup.canvas.requests();
+    assert!(!requests.contains(&"GET /api/v1/folders/501/files".to_owned()));
+    assert!(requests.contains(&"GET /api/v1/folders/502/files".to_owned()));
+}
+
+#[test]
+fn sync_exports_assignments() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let homework = setup.user_dir().join("Intro to Testing").join("Assignments").join("Homework 1");
+    let description = setup.read(homework.join("description.html"));
+    assert!(description.contains("<h1>Homework 1</h1>"));
+    assert!(description.contains("the slides"));
+    let metadata = setup.read(homework.join("assignment.json"));
+    assert!(metadata.contains("\"points_possible\": 10.0"));
+    assert!(metadata.contains("\"due_at\""));
+    assert_eq!(setup.read(homework.join("lecture 1.pdf")), "lecture 1 slides");
+}
+
+#[test]
+fn sync_keeps_assignments_with_the_same_name_apart() {
+    let setup = Setup::new("basic");
+    setup.canvas.edit("courses/101/assignments", |assignments| {
+        let mut homework = assignments[0].clone();
+        homework["id"] = json!(602);
+        homework["description"] = json!("<p>Do it again.</p>");
+        assignments.as_array_mut().unwrap().insert(0, homework);
+    });
+    setup.app.sync().unwrap();
+    let assignments = setup.user_dir().join("Intro to Testing").join("Assignments");
+    // the older one keeps the name
+    let first = setup.read(assignments.join("Homework 1").join("description.html"));
+    assert!(first.contains("the slides"));
+    assert!(assignments.join("Homework 1").join("submission").is_dir());
+    let second = setup.read(assignments.join("Homework 1 (602)").join("description.html"));
+    assert!(second.contains("Do it again."));
+    assert!(!assignments.join("Homework 1 (602)").join("submission").exists());
+}
+
+#[test]
+fn sync_keeps_exports_apart_from_canvas_ids() {
+    // module 1 and file 2 have the same ids that Assignments and an assignment's submission used to,
+    // and the comment's attachment, file 1, has the same id as attempt 1 used to
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    let homework = course.join("Assignments").join("Homework 1");
+    assert_eq!(setup.read(course.join("Orientation").join("rules.txt")), "rules");
+    assert_eq!(setup.read(homework.join("rules.txt")), "rules");
+    assert!(homework.join("submission").join("feedback.html").is_file());
+    assert_eq!(setup.read(homework.join("submission").join("notes.txt")), "notes");
+    let state: Value = serde_json::from_str(&setup.sync_json()).unwrap();
+    let courses = &state["canvases"][0]["users"][0]["file_tree"]["root"]["files"][0]["Directory"]["files"];
+    let course = courses
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|it| it["Directory"]["base"]["id"]["name"] == "Intro to Testing")
+        .unwrap();
+    let names = course["Directory"]["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|it| it["Directory"]["base"]["id"]["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names.iter().filter(|it| **it == "Orientation").count(), 1);
+    assert_eq!(names.iter().filter(|it| **it == "Assignments").count(), 1);
+}
+
+#[test]
+fn sync_downloads_submissions_and_feedback() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let submission = setup.user_dir()
+        .join("Intro to Testing")
+        .join("Assignments")
+        .join("Homework 1")
+        .join("submission");
+    // every attempt is kept
+    assert_eq!(setup.read(submission.join("attempt 1").join("homework.txt")), "draft");
+    assert_eq!(setup.read(submission.join("attempt 2").join("homework.txt")), "final");
+    assert_eq!(setup.read(submission.join("notes.txt")), "notes");
+    let feedback = setup.read(submission.join("feedback.html"));
+    assert!(feedback.contains("Grade: 9 (9 / 10)"));
+    assert!(feedback.contains("<td>Clarity</td><td>Clear</td><td>5 / 5</td><td>Very clear</td>"));
+    assert!(feedback.contains("<td>Tests</td><td>Thorough</td><td>4 / 5</td>"));
+    assert!(feedback.contains("<strong>Professor Mock</strong>"));
+    assert!(feedback.contains("Nice work.<br>\nSee my notes."));
+    let requests = setup.canvas.requests();
+    assert!(requests.contains(&"GET /api/v1/courses/101/assignments/601/submissions/self".to_owned()));
+}
+
+#[test]
+fn sync_skips_submissions_that_fail() {
+    let setup = Setup::new("basic");
+    setup.set_setting("retry", json!({ "max_attempts": 1 }));
+    setup.canvas.fail_endpoint("courses/101/assignments/601/submissions/self", 500);
+    setup.app.sync().unwrap();
+    let homework = setup.user_dir().join("Intro to Testing").join("Assignments").join("Homework 1");
+    assert!(homework.join("description.html").is_file());
+    assert!(!homework.join("submission").exists());
+    assert!(setup.user_dir().join("Intro to Testing").join("Week 1").is_dir());
+}
+
+#[test]
+fn sync_exports_pages_as_html() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let pages = setup.user_dir().join("Intro to Testing").join("Pages");
+    let notes = setup.read(pages.join("Lecture Notes.html"));
+    assert!(notes.contains("<title>Lecture Notes</title>"));
+    assert!(notes.contains("<h2>Week 1</h2>"));
+    // locked pages have no body to export
+    assert!(!pages.join("Exam Answers.html").exists());
+}
+
+#[test]
+fn sync_names_exports_without_slashes() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let pages = setup.user_dir().join("Intro to Testing").join("Pages");
+    let review = setup.read(pages.join("Review 1_2.html"));
+    // only the file name is changed
+    assert!(review.contains("<h1>Review 1/2</h1>"));
+    assert!(!pages.join("Review 1").exists());
+    // attachments are named by whoever uploaded them
+    let topic = setup.user_dir().join("Intro to Testing").join("Discussions").join("Introductions");
+    assert_eq!(setup.read(topic.join("a_b.txt")), "a / b");
+    assert!(!topic.join("a").exists());
+}
+
+#[test]
+fn sync_keeps_pages_with_the_same_title_apart() {
+    let setup = Setup::new("basic");
+    setup.canvas.edit("courses/101/pages", |pages| {
+        let mut notes = pages[0].clone();
+        notes["page_id"] = json!(705);
+        notes["body"] = json!("<p>More notes.</p>");
+        let mut review = pages[2].clone();
+        review["page_id"] = json!(706);
+        review["title"] = json!("Review 1_2");
+        review["body"] = json!("<p>Not the same review.</p>");
+        pages.as_array_mut().unwrap().extend(vec![notes, review]);
+    });
+    setup.app.sync().unwrap();
+    let pages = setup.user_dir().join("Intro to Testing").join("Pages");
+    assert!(setup.read(pages.join("Lecture Notes.html")).contains("<h2>Week 1</h2>"));
+    assert!(setup.read(pages.join("Lecture Notes (705).html")).contains("More notes."));
+    // titles that are only the same once they're file names
+    assert!(setup.read(pages.join("Review 1_2.html")).contains("Halfway there."));
+    assert!(setup.read(pages.join("Review 1_2 (706).html")).contains("Not the same review."));
+}
+
+#[test]
+fn sync_exports_pages_as_markdown() {
+    let setup = Setup::new("basic");
+    setup.set_setting("documents", json!("markdown"));
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    let notes = setup.read(course.join("Pages").join("Lecture Notes.md"));
+    assert_eq!(notes, "\
+# Lecture Notes
+
+## Week 1
+
+Testing is **fun** & useful.
+
+- Read the [syllabus](../Files/syllabus.pdf)
+- Write tests
+");
+    assert!(course.join("Assignments").join("Homework 1").join("description.md").is_file());
+}
+
+#[test]
+fn sync_exports_edited_pages_again() {
+    let setup = Setup::new("basic");
+    setup.set_setting("documents", json!("markdown"));
+    setup.app.sync().unwrap();
+    let notes = setup.user_dir().join("Intro to Testing").join("Pages").join("Lecture Notes.md");
+    let edit = |updated_at: &str| {
+        setup.canvas.edit("courses/101/pages", |pages| {
+            let notes = &mut pages[0];
+            notes["body"] = json!("<h2>Week 2</h2><p>Mocking is <em>also</em> fun.</p>");
+            notes["updated_at"] = json!(updated_at);
+        });
+    };
+    // not exported again until canvas says it's been updated
+    edit("2020-01-17T12:00:00Z");
+    setup.app.sync().unwrap();
+    assert!(setup.read(&notes).contains("## Week 1"));
+    edit("2020-01-24T12:00:00Z");
+    setup.app.sync().unwrap();
+    assert_eq!(setup.read(&notes), "\
+# Lecture Notes
+
+## Week 2
+
+Mocking is _also_ fun.
+");
+}
+
+#[test]
+fn sync_rewrites_file_links_in_documents() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let course = setup.user_dir().join("Intro to Testing");
+    // the copy next to the description is preferred
+    let description = setup.read(course.join("Assignments").join("Homework 1").join("description.html"));
+    assert!(description.contains(r#"<a href="lecture%201.pdf">the slides</a>"#));
+    let notes = setup.read(course.join("Pages").join("Lecture Notes.html"));
+    assert!(notes.contains(r#"<a href="../Files/syllabus.pdf">syllabus</a>"#));
+    // files that aren't in a visible folder are synced at the top of Files
+    let handouts = setup.read(course.join("Pages").join("Handouts.html"));
+    assert!(handouts.contains(r#"href="../Files/handout.pdf">the handout</a>"#));
+    assert!(handouts.contains("https://elsewhere.example.com/files/9"));
+    assert_eq!(setup.read(course.join("Files").join("handout.pdf")), "handout contents");
+}
+
+#[test]
+fn sync_exports_discussions_with_replies() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let topic = setup.user_dir().join("Intro to Testing").join("Discussions").join("Introductions");
+    let thread = setup.read(topic.join("discussion.html"));
+    assert!(thread.contains("<strong>Professor Mock</strong>"));
+    assert!(thread.contains("<p>Say hi!</p>"));
+    // replies are nested under what they replied to
+    let hello = thread.find("Hello, I like tests.").unwrap();
+    let welcome = thread.find("Welcome!").unwrap();
+    assert!(hello < welcome);
+    assert_eq!(thread[hello..welcome].matches("<ul>").count(), 1);
+    assert!(thread.contains("<em>deleted</em>"));
+    assert_eq!(setup.read(topic.join("introductions.txt")), "introductions");
+    assert_eq!(setup.read(topic.join("my hobbies.txt")), "tests, tests");
+}
+
+#[test]
+fn sync_keeps_discussions_and_attachments_with_the_same_name_apart() {
+    let setup = Setup::new("basic");
+    setup.canvas.edit("courses/101/discussion_topics", |topics| {
+        let mut again = topics[0].clone();
+        again["id"] = json!(802);
+        again["message"] = json!("<p>Say hi again!</p>");
+        again["attachments"] = json!([]);
+        topics.as_array_mut().unwrap().push(again);
+    });
+    setup.canvas.edit("courses/101/discussion_topics/801/view", |view| {
+        view["view"][0]["replies"][0]["attachments"].as_array_mut().unwrap().push(json!({
+            "id": 410,
+            "display_name": "discussion.html",
+            "filename": "discussion.html",
+            "content-type": "text/html",
+            "size": 14,
+            "created_at": "2020-01-12T12:00:00Z",
+            "updated_at": "2020-01-12T12:00:00Z",
+            "modified_at": "2020-01-12T12:00:00Z",
+        }));
+    });
+    setup.app.sync().unwrap();
+    let discussions = setup.user_dir().join("Intro to Testing").join("Discussions");
+    let topic = discussions.join("Introductions");
+    assert!(setup.read(topic.join("discussion.html")).contains("Hello, I like tests."));
+    assert_eq!(setup.read(topic.join("discussion (410).html")), "not the thread");
+    let again = setup.read(discussions.join("Introductions (802)").join("discussion.html"));
+    assert!(again.contains("Say hi again!"));
+}
+
+#[test]
+fn sync_exports_discussions_as_markdown() {
+    let setup = Setup::new("basic");
+    setup.set_setting("documents", json!("markdown"));
+    setup.app.sync().unwrap();
+    let topic = setup.user_dir().join("Intro to Testing").join("Discussions").join("Introductions");
+    let thread = setup.read(topic.join("discussion.md"));
+    assert!(thread.contains("\n- **Test Student** "));
+    assert!(thread.contains("\n    Hello, I like tests.\n"));
+    assert!(thread.contains("\n    - **Professor Mock** "));
+    assert!(thread.contains("\n        Welcome!\n"));
+}
+
+#[test]
+fn sync_exports_discussions_again_after_new_replies() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let thread = setup.user_dir().join("Intro to Testing").join("Discussions").join("Introductions").join("discussion.html");
+    let reply = |last_reply_at: &str| {
+        setup.canvas.edit("courses/101/discussion_topics/801/view", |view| {
+            view["view"].as_array_mut().unwrap().push(json!({
+                "id": 904,
+                "user_id": 2,
+                "parent_id": null,
+                "created_at": last_reply_at,
+                "updated_at": last_reply_at,
+                "message": "<p>Don't forget to introduce yourselves.</p>",
+            }));
+        });
+        setup.canvas.edit("courses/101/discussion_topics", |topics| {
+            topics[0]["last_reply_at"] = json!(last_reply_at);
+        });
+    };
+    // a thread without new replies isn't exported again
+    std::fs::remove_file(&thread).unwrap();
+    setup.app.sync().unwrap();
+    assert!(!thread.exists());
+    reply("2020-01-20T12:00:00Z");
+    setup.app.sync().unwrap();
+    let contents = setup.read(&thread);
+    assert!(contents.contains("Hello, I like tests."));
+    assert!(contents.contains("Don't forget to introduce yourselves."));
+}
+
+#[test]
+fn sync_archives_announcements() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let announcements = setup.user_dir().join("Intro to Testing").join("Announcements");
+    let welcome = setup.read(announcements.join("2020-01-13 Welcome.html"));
+    assert!(welcome.contains("<strong>Professor Mock</strong>"));
+    assert!(welcome.contains(r#"<a href="Attachments/schedule.txt">schedule</a>"#));
+    assert_eq!(setup.read(announcements.join("Attachments").join("schedule.txt")), "schedule");
+    // announcements posted the same day with the same title don't overwrite each other
+    let office_hours = setup.read(announcements.join("2020-01-13 Welcome (1003).html"));
+    assert!(office_hours.contains("Office hours are on Fridays."));
+    // only each course's own announcements
+    assert!(!announcements.join("2020-01-14 Mocking starts Monday.html").exists());
+    let mocking = setup.user_dir().join("Advanced Mocking").join("Announcements");
+    assert!(mocking.join("2020-01-14 Mocking starts Monday.html").is_file());
+    let requests = setup.canvas.requests();
+    assert!(requests.contains(&"GET /api/v1/announcements".to_owned()));
+}
+
+#[test]
+fn sync_only_writes_new_announcements() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let announcements = setup.user_dir().join("Intro to Testing").join("Announcements");
+    // so any announcement written again shows up
+    let welcome = announcements.join("2020-01-13 Welcome.html");
+    let schedule = announcements.join("Attachments").join("schedule.txt");
+    std::fs::remove_file(&welcome).unwrap();
+    std::fs::remove_file(&schedule).unwrap();
+    setup.app.sync().unwrap();
+    assert!(!welcome.exists());
+    assert!(!schedule.exists());
+    setup.canvas.edit("announcements", |announcements| {
+        announcements.as_array_mut().unwrap().push(json!({
+            "id": 1004,
+            "title": "Midterm",
+            "message": "<p>It's next week.</p>",
+            "posted_at": "2020-02-01T12:00:00Z",
+            "user_name": "Professor Mock",
+            "context_code": "course_101",
+        }));
+    });
+    setup.app.sync().unwrap();
+    assert!(announcements.join("2020-02-01 Midterm.html").is_file());
+    assert!(!welcome.exists());
+}
+
+#[test]
+fn sync_follows_course_pagination() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let user = setup.user_dir();
+    for course in &["Intro to Testing", "Advanced Mocking", "Fixtures and You", "Pagination Seminar"] {
+        assert!(user.join(course).is_dir(), "{} wasn't synced", course);
+    }
+}
+
+#[test]
+fn sync_requests_courses_and_modules() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let requests = setup.canvas.requests();
+    assert!(requests.contains(&"GET /api/v1/courses".to_owned()));
+    assert!(requests.contains(&"GET /api/v1/courses/101/modules".to_owned()));
+    assert!(requests.contains(&"GET /api/v1/courses/101/folders".to_owned()));
+    assert!(requests.contains(&"GET /api/v1/folders/502/files".to_owned()));
+}
+
+#[test]
+fn sync_retries_rate_limited_requests() {
+    let unlimited = Setup::new("basic");
+    unlimited.app.sync().unwrap();
+    let setup = Setup::new("basic");
+    setup.canvas.rate_limit_next(2);
+    setup.app.sync().unwrap();
+    assert!(setup.user_dir().join("Intro to Testing").join("Week 1").is_dir());
+    assert_eq!(setup.canvas.requests().len(), unlimited.canvas.requests().len() + 2);
+}
+
+#[test]
+fn sync_retries_server_errors() {
+    let reliable = Setup::new("basic");
+    reliable.app.sync().unwrap();
+    let setup = Setup::new("basic");
+    setup.canvas.fail_next(2);
+    setup.app.sync().unwrap();
+    assert!(setup.user_dir().join("Intro to Testing").join("Week 1").is_dir());
+    assert_eq!(setup.canvas.requests().len(), reliable.canvas.requests().len() + 2);
+}
+
+#[test]
+fn sync_gives_up_after_max_attempts() {
+    let setup = Setup::new("basic");
+    setup.canvas.fail_next(RetryPolicy::default().max_attempts as usize);
+    assert!(setup.app.sync().is_err());
+}
+
+#[test]
+fn sync_keeps_access_token_out_of_sync_json() {
+    let setup = Setup::new("basic");
+    setup.app.sync().unwrap();
+    let sync_json = setup.sync_json();
+    assert!(sync_json.contains("Intro to Testing"));
+    assert!(!sync_json.contains(setup.canvas.access_token()));
+}
+
+#[test]
+fn sync_fails_without_access_token() {
+    let setup = Setup::new("basic");
+    let missing = TempDir::new("missing");
+    let app = CanvasFileSync {
+        credentials: missing.path().join("credentials.json"),
+        ..setup.app
+    };
+    assert!(app.sync().is_err());
+    assert!(setup.canvas.requests().is_empty());
+}
+
+#[test]
+fn add_user_still_selects_from_a_single_canvas() {
+    let setup = Setup::new("basic");
+    let add_user = || AddUser {
+        access_token: setup.canvas.access_token().into(),
+        search: CANVAS.into(),
+        domain: None,
+        base_url: Some(setup.canvas.base_url().to_string()),
+    };
+    // like --pick 2
+    let e = setup.app.add_user(add_user(), |_| Err("--pick 2 is out of range".into())).unwrap_err();
+    assert_eq!(e.to_string(), "--pick 2 is out of range");
+    assert!(setup.canvas.requests().is_empty());
+    setup.app.add_user(add_user(), |mut canvases| Ok(canvases.remove(0))).unwrap();
+    assert_eq!(setup.canvas.requests(), vec!["GET /api/v1/users/self".to_owned()]);
+}
+
+#[test]
+fn sync_replays_recorded_cassette_offline() {
+    let setup = Setup::new("basic");
+    // like someone debugging it, with the sync.json from before the sync, but not the access token
+    let replay_dir = TempDir::new("replay");
+    std::fs::copy(SyncState::path(setup.sync.path()), SyncState::path(replay_dir.path())).unwrap();
+    let cassettes = TempDir::new("cassettes");
+    let cassette = cassettes.path().join("sync.json");
+    let recording = CanvasFileSync {
+        record: Some(cassette.clone()),
+        ..setup.app
+    };
+    recording.sync().unwrap();
+    let recorded = std::fs::read_to_string(&cassette).unwrap();
+    assert!(recorded.contains("Intro to Testing"));
+    assert!(!recorded.contains(setup.canvas.access_token()));
+    let no_credentials = TempDir::new("no-credentials");
+    let sent = setup.canvas.requests().len();
+    let replay = CanvasFileSync {
+        dir: replay_dir.path().to_owned(),
+        skip_git: true,
+        lock_timeout: Some(Duration::from_secs(5)),
+        credentials: no_credentials.path().join("credentials.json"),
+        record: None,
+        replay: Some(cassette),
+    };
+    let before = std::fs::read_to_string(SyncState::path(replay_dir.path())).unwrap();
+    replay.sync().unwrap();
+    let user = replay_dir.path().join(CANVAS).join(USER);
+    assert!(user.join("Intro to Testing").join("Week 1").is_dir());
+    assert!(user.join("Pagination Seminar").is_dir());
+    assert_eq!(setup.canvas.requests().len(), sent);
+    // so replaying again syncs the same files
+    assert_eq!(std::fs::read_to_string(SyncState::path(replay_dir.path())).unwrap(), before);
+    replay.sync().unwrap();
+    assert_eq!(setup.canvas.requests().len(), sent);
+}
@@ -0,0 +1,62 @@
+mod mock;
+
+use crate::mock::{Fixture, MockCanvas};
+use async_std::task;
+use canvas_file_sync::api::transport::{self, FakeTransport, Method, RecordingTransport, ReplayTransport};
+use canvas_file_sync::api::CoreApi;
+use serde_json::json;
+use std::sync::Arc;
+
+#[test]
+fn fake_transport_answers_without_network() {
+    let fake = Arc::new(FakeTransport::new());
+    fake.respond_json(Method::Get, "https://canvas.test/api/v1/users/self", &json!({
+        "id": 1,
+        "name": "Test Student",
+    }));
+    let base_url = CoreApi::parse_base_url("canvas.test").unwrap();
+    let api = CoreApi::new(base_url, "token".into()).with_transport(fake.clone());
+    let user = task::block_on(api.current_user()).unwrap();
+    assert_eq!(user.name, "Test Student");
+    let requests = fake.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].header("Authorization"), Some("Bearer token"));
+}
+
+#[test]
+fn recorded_exchanges_replay_without_canvas() {
+    let canvas = MockCanvas::start(Fixture::load("basic"));
+    let recorder = Arc::new(RecordingTransport::new(transport::default()));
+    let api = CoreApi::new(canvas.base_url(), canvas.access_token().into());
+    let recording = api.clone().with_transport(recorder.clone());
+    let recorded = task::block_on(recording.courses()).unwrap();
+    let sent = canvas.requests().len();
+    let replay = api.with_transport(Arc::new(ReplayTransport::new(recorder.exchanges())));
+    let replayed = task::block_on(replay.courses()).unwrap();
+    let names = |courses: Vec<canvas_file_sync::api::course::Course>| {
+        courses
+            .into_iter()
+            .map(|it| it.name)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(replayed), names(recorded));
+    assert_eq!(canvas.requests().len(), sent);
+}
+
+#[test]
+fn queries_repeat_the_key_of_each_array_item() {
+    let fake = Arc::new(FakeTransport::new());
+    fake.respond_json(Method::Get, "https://canvas.test/api/v1/items", &json!([]));
+    let base_url = CoreApi::parse_base_url("canvas.test").unwrap();
+    let api = CoreApi::new(base_url, "token".into()).with_transport(fake.clone());
+    let query = json!({
+        "include[]": ["items", "body"],
+        "per_page": 50,
+        "search_term": null,
+    });
+    task::block_on(api.get::<_, Vec<serde_json::Value>>("items", &query)).unwrap();
+    assert_eq!(
+        fake.requests()[0].url,
+        "https://canvas.test/api/v1/items?include%5B%5D=items&include%5B%5D=body&per_page=50",
+    );
+}